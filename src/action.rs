@@ -0,0 +1,123 @@
+/// Everything the user can trigger, from key bindings or the command palette
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    ToggleDebug,
+    ReverseLogs,
+    ToggleTimestamps,
+    ClearLogs,
+    GrowLogs,
+    ShrinkLogs,
+    ToggleCompact,
+    FocusNext,
+    FocusPrevious,
+    FindNode,
+    PreviewConfig,
+    Help,
+}
+
+impl Action {
+    pub const ALL: &'static [Action] = &[
+        Action::Quit,
+        Action::ToggleDebug,
+        Action::ReverseLogs,
+        Action::ToggleTimestamps,
+        Action::ClearLogs,
+        Action::GrowLogs,
+        Action::ShrinkLogs,
+        Action::ToggleCompact,
+        Action::FocusNext,
+        Action::FocusPrevious,
+        Action::FindNode,
+        Action::PreviewConfig,
+        Action::Help,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::ToggleDebug => "Toggle tracing logs",
+            Action::ReverseLogs => "Reverse log order",
+            Action::ToggleTimestamps => "Toggle log timestamps",
+            Action::ClearLogs => "Clear log pane",
+            Action::GrowLogs => "Grow log pane",
+            Action::ShrinkLogs => "Shrink log pane",
+            Action::ToggleCompact => "Toggle compact status line",
+            Action::FocusNext => "Focus next pane",
+            Action::FocusPrevious => "Focus previous pane",
+            Action::FindNode => "Find node",
+            Action::PreviewConfig => "Preview node config.yaml",
+            Action::Help => "Show help",
+        }
+    }
+
+    /// Actions whose title fuzzy matches `query`, best match first
+    pub fn matching(query: &str) -> Vec<Action> {
+        let mut matches = Self::ALL
+            .iter()
+            .filter_map(|action| Some((fuzzy_score(query, action.title())?, *action)))
+            .collect::<Vec<_>>();
+        // Stable, so equal scores keep their `ALL` order
+        matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+
+        matches.into_iter().map(|(_, action)| action).collect()
+    }
+}
+
+/// Case-insensitive subsequence match of `query` against `text`, higher is better. Runs of
+/// consecutive characters and characters starting a word score extra.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<u32> {
+    let text = text.to_lowercase().chars().collect::<Vec<_>>();
+
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let index = position + text[position..].iter().position(|t| *t == c)?;
+
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == index) {
+            score += 2;
+        }
+        if index == 0 || text[index - 1] == ' ' {
+            score += 3;
+        }
+
+        previous = Some(index);
+        position = index + 1;
+    }
+
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_score_prefers_word_starts_and_runs() {
+        assert_eq!(fuzzy_score("", "Quit"), Some(0));
+        assert_eq!(fuzzy_score("qx", "Quit"), None);
+        assert_eq!(fuzzy_score("tiuq", "Quit"), None);
+
+        assert!(fuzzy_score("qu", "Quit") > fuzzy_score("qt", "Quit"));
+        assert!(fuzzy_score("rl", "Reverse log order") > fuzzy_score("rl", "Clear log pane"));
+        assert_eq!(fuzzy_score("QUIT", "quit"), fuzzy_score("quit", "Quit"));
+    }
+
+    #[test]
+    fn matching_orders_by_score_then_listing() {
+        assert_eq!(Action::matching(""), Action::ALL);
+        assert_eq!(Action::matching("clear"), [Action::ClearLogs]);
+        assert_eq!(
+            Action::matching("tog"),
+            [
+                Action::ToggleDebug,
+                Action::ToggleTimestamps,
+                Action::ToggleCompact
+            ]
+        );
+        assert_eq!(Action::matching("rev")[0], Action::ReverseLogs);
+        assert!(Action::matching("zzz").is_empty());
+    }
+}
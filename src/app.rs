@@ -1,21 +1,114 @@
 use std::{
     io::{self, Stdout},
     sync::Arc,
+    time::{Duration, Instant},
 };
 
-use crossterm::event::{KeyEvent, KeyModifiers};
+use crossterm::{
+    event::{DisableBracketedPaste, EnableBracketedPaste, KeyEvent, KeyModifiers},
+    execute,
+    style::Print,
+    terminal::SetTitle,
+};
 use futures::lock::Mutex;
 use ratatui::{
-    backend::CrosstermBackend,
+    backend::{Backend, CrosstermBackend},
+    buffer::Buffer,
     crossterm::event::KeyCode,
-    layout::{Constraint, Layout},
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Color, Modifier, Style},
     symbols,
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
     Frame, Terminal,
 };
-use tracing::debug;
+use tracing::{debug, Level};
+
+use crate::{
+    action::Action,
+    config::{Config, Node},
+    event::EventHandler,
+    log::{self, LogEvent, LogFormat},
+    metrics::Metrics,
+    rke2,
+};
+
+// xterm title stack, lets us put back whatever title the terminal had before we started
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
+
+/// Which set of key bindings is active, so e.g. `q` only quits from `Normal`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputMode {
+    #[default]
+    Normal,
+    Search,
+    Confirm,
+    Detail,
+    Help,
+    Palette,
+}
+
+/// The pane keyboard navigation applies to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Focus {
+    #[default]
+    Clusters,
+    Control,
+    Worker,
+    Logs,
+}
+
+impl Focus {
+    const ORDER: [Focus; 4] = [Focus::Clusters, Focus::Control, Focus::Worker, Focus::Logs];
+
+    /// Step through the panes, skipping the cluster menu and log pane while they're hidden
+    fn cycle(self, forward: bool, menu_shown: bool, logs_shown: bool) -> Self {
+        let order = Self::ORDER
+            .into_iter()
+            .filter(|pane| match pane {
+                Focus::Clusters => menu_shown,
+                Focus::Logs => logs_shown,
+                Focus::Control | Focus::Worker => true,
+            })
+            .collect::<Vec<_>>();
+        let index = order.iter().position(|pane| *pane == self).unwrap_or(0);
+        let index = if forward {
+            (index + 1) % order.len()
+        } else {
+            (index + order.len() - 1) % order.len()
+        };
+
+        order[index]
+    }
+}
+
+const HELP: &[(&str, &str)] = &[
+    ("q / Esc", "Quit"),
+    ("Ctrl-C", "Quit from anywhere"),
+    ("d", "Toggle tracing logs"),
+    ("r", "Reverse log order"),
+    ("T", "Toggle log timestamps"),
+    ("Backspace", "Clear the log pane"),
+    ("+ / -", "Grow or shrink the log pane"),
+    ("c", "Toggle compact status line"),
+    ("Tab / S-Tab", "Cycle pane focus"),
+    ("f", "Find a node in the focused pane"),
+    ("Enter", "Preview the found node's config.yaml"),
+    ("1-9", "Switch cluster"),
+    ("?", "Show this help"),
+    ("Ctrl-P", "Command palette"),
+];
+
+/// Below this many columns the cluster menu is dropped and panes are stacked
+const NARROW_WIDTH: u16 = 80;
+
+/// Bounds for the log pane height, as a percentage of the screen
+const LOG_HEIGHT_RANGE: (u16, u16) = (10, 90);
+const LOG_HEIGHT_STEP: u16 = 5;
 
-use crate::{config::Config, event::EventHandler, log::LogEvent};
+/// Minimum gap between bells so an error storm doesn't beep continuously
+const BELL_DEBOUNCE: Duration = Duration::from_secs(5);
 
 pub struct App<T>
 where
@@ -23,51 +116,210 @@ where
 {
     running: bool,
     debug: bool,
+    reverse_logs: bool,
+    log_format: LogFormat,
+    compact: bool,
+    log_height: u16,
+    privileged: bool,
+    color: bool,
+    ascii_borders: bool,
+    /// Terminal is below `NARROW_WIDTH`, so the cluster menu is hidden
+    narrow: bool,
+    mode: InputMode,
+    focus: Focus,
+    /// Node picked by find in the focused pane
+    selected_node: Option<usize>,
+    input: String,
+    palette_selected: usize,
     terminal: Arc<Mutex<Terminal<T>>>,
     events: EventHandler,
     logs: Vec<LogEvent>,
+    last_bell: Option<Instant>,
+    /// Exported by `--metrics`, kept in step with the selected cluster
+    metrics: Option<Arc<Metrics>>,
     config: Config,
 }
 
 impl App<CrosstermBackend<Stdout>> {
-    pub fn new(events: EventHandler, config: Config) -> Self {
-        let terminal = Arc::new(Mutex::new(ratatui::init()));
+    /// Fails when the terminal can't enter raw mode or the alternate screen, leaving it as it
+    /// was found
+    pub fn new(events: EventHandler, config: Config) -> io::Result<Self> {
+        let terminal = init_terminal(ratatui::try_init, ratatui::restore)?;
+
+        Ok(Self::with_terminal(terminal, events, config))
+    }
+
+    pub async fn run(mut self) -> io::Result<()> {
+        self.running = true;
+
+        let terminal = self.terminal.clone();
+        let mut terminal = terminal.lock().await;
+
+        // Title escapes are best effort, terminals without support simply ignore them
+        let mut title = self.title();
+        execute!(io::stdout(), Print(PUSH_TITLE), SetTitle(&title)).ok();
+        // Without bracketed paste, pasted text arrives as a stream of key presses
+        execute!(io::stdout(), EnableBracketedPaste).ok();
+
+        terminal.clear()?;
+        let mut result = Ok(());
+        while self.running {
+            terminal.draw(|frame| self.render(frame))?;
+            if let Err(err) = self.handle_events().await {
+                result = Err(err);
+                break;
+            }
+            // Follow cluster switches here, away from any backend tests draw to
+            if self.title() != title {
+                title = self.title();
+                execute!(io::stdout(), SetTitle(&title)).ok();
+            }
+        }
+
+        execute!(io::stdout(), DisableBracketedPaste).ok();
+        ratatui::restore();
+        execute!(io::stdout(), Print(POP_TITLE)).ok();
+        result
+    }
+}
+
+impl<T> App<T>
+where
+    T: Backend,
+{
+    /// App drawing to any terminal, e.g. a `TestBackend` one
+    pub fn with_terminal(terminal: Terminal<T>, events: EventHandler, config: Config) -> Self {
+        let narrow = terminal.size().is_ok_and(|size| size.width < NARROW_WIDTH);
+        let terminal = Arc::new(Mutex::new(terminal));
         let logs = vec![];
 
         Self {
             running: false,
             debug: false,
+            reverse_logs: false,
+            log_format: LogFormat::default(),
+            compact: false,
+            log_height: clamp_log_height(config.ui.log_height),
+            privileged: is_privileged(),
+            color: true,
+            ascii_borders: config.ui.ascii_borders,
+            narrow,
+            mode: InputMode::default(),
+            focus: default_focus(narrow),
+            selected_node: None,
+            input: String::new(),
+            palette_selected: 0,
             terminal,
             events,
             logs,
+            last_bell: None,
+            metrics: None,
             config,
         }
     }
 
-    pub async fn run(mut self) -> io::Result<()> {
-        self.running = true;
+    /// Seed the log pane, e.g. with the tail of a previous run's log file
+    pub fn with_logs(mut self, mut logs: Vec<LogEvent>) -> Self {
+        logs.sort_by_key(LogEvent::instant);
+        self.logs = logs;
 
-        let terminal = self.terminal.clone();
-        let mut terminal = terminal.lock().await;
+        self
+    }
 
-        terminal.clear()?;
-        while self.running {
-            terminal.draw(|frame| self.draw(frame))?;
-            self.handle_events().await;
+    /// Config warnings go straight into the log pane, which opens so they get seen.
+    /// Logging them through `tracing` would hide them behind `RUST_LOG`.
+    pub fn with_warnings(mut self, warnings: Vec<String>) -> Self {
+        if warnings.is_empty() {
+            return self;
         }
 
-        ratatui::restore();
-        Ok(())
+        for warning in warnings {
+            self.handle_log(LogEvent::new(Level::WARN, "ez_rke::config", &warning));
+        }
+        self.debug = true;
+
+        self
+    }
+
+    /// Report the selected cluster's nodes on the metrics endpoint
+    pub fn with_metrics(mut self, metrics: Option<Arc<Metrics>>) -> Self {
+        if let Some(metrics) = &metrics {
+            metrics.set_nodes(self.config.servers());
+        }
+        self.metrics = metrics;
+
+        self
+    }
+
+    /// Turn colours off for terminals that lack them or users who asked for none
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+
+        self
+    }
+
+    /// Force ASCII borders regardless of the config
+    pub fn with_ascii_borders(mut self, ascii_borders: bool) -> Self {
+        self.ascii_borders |= ascii_borders;
+
+        self
+    }
+
+    fn title(&self) -> String {
+        match self.config.cluster_name() {
+            Some(name) => format!("ez-rke: {name}"),
+            None => "ez-rke".to_string(),
+        }
+    }
+
+    /// Draw a frame, then apply the colour and border settings to the finished buffer
+    fn render(&self, frame: &mut Frame) {
+        self.draw(frame);
+        if !self.color {
+            strip_colors(frame.buffer_mut());
+        }
+        if self.ascii_borders {
+            ascii_borders(frame.buffer_mut());
+        }
     }
 
     fn draw(&self, frame: &mut Frame) {
+        if self.compact {
+            frame.render_widget(Paragraph::new(self.summary()), frame.area());
+            self.draw_popup(frame);
+            return;
+        }
+
+        let area = if self.privileged {
+            frame.area()
+        } else {
+            let [banner, area] =
+                Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(frame.area());
+            frame.render_widget(
+                Paragraph::new("Not running as root, some operations may lack permissions")
+                    .style(Style::default().fg(Color::Black).bg(Color::Yellow)),
+                banner,
+            );
+            area
+        };
+
+        if self.narrow {
+            self.draw_narrow(frame, area);
+            self.draw_popup(frame);
+            return;
+        }
+
         let mut left_block = Block::new()
             .borders(Borders::ALL ^ Borders::RIGHT)
+            .border_style(self.border_style(Focus::Clusters))
             .title("Configuration");
 
         let (main_area, border_set) = if self.debug {
-            let split = Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)])
-                .split(frame.area());
+            let split = Layout::vertical([
+                Constraint::Percentage(100 - self.log_height),
+                Constraint::Percentage(self.log_height),
+            ])
+            .split(area);
 
             let border_set = symbols::border::Set {
                 bottom_left: symbols::line::NORMAL.vertical_right,
@@ -79,12 +331,14 @@ impl App<CrosstermBackend<Stdout>> {
                 .border_set(border_set);
 
             let log_area = split[1];
-            let height = log_area.height;
-            let len = self.logs.len();
 
-            let mut state = ListState::default()
-                .with_offset(len.saturating_sub((height as usize).saturating_sub(1)));
-            frame.render_stateful_widget(self.draw_logs(), log_area, &mut state);
+            let mut state = ListState::default().with_offset(self.log_offset(log_area.height));
+            let block = Block::new().borders(Borders::ALL ^ Borders::TOP);
+            frame.render_stateful_widget(
+                self.draw_logs(block, log_area.width),
+                log_area,
+                &mut state,
+            );
 
             let border_set = symbols::border::Set {
                 bottom_left: symbols::line::NORMAL.horizontal_up,
@@ -97,23 +351,29 @@ impl App<CrosstermBackend<Stdout>> {
                 bottom_left: symbols::line::NORMAL.horizontal_up,
                 ..symbols::border::PLAIN
             };
-            (frame.area(), border_set)
+            (area, border_set)
         };
 
         let split =
             Layout::horizontal([Constraint::Min(20), Constraint::Percentage(100)]).split(main_area);
 
-        let mut config_state = ListState::default();
+        let mut config_state = ListState::default().with_selected(self.selected_cluster());
 
         frame.render_stateful_widget(
-            List::new(vec!["Test config"]).block(left_block),
+            List::new(self.menu_items())
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+                .block(left_block),
             split[0],
             &mut config_state,
         );
 
-        let (control_server_area, border_set) = if let Some(vip) = &self.config.servers.vip {
-            let split =
-                Layout::vertical([Constraint::Min(2), Constraint::Percentage(100)]).split(split[1]);
+        let vips = self.vip_lines();
+        let (control_server_area, border_set) = if !vips.is_empty() {
+            let split = Layout::vertical([
+                Constraint::Length(vips.len() as u16 + 1),
+                Constraint::Percentage(100),
+            ])
+            .split(split[1]);
 
             let border_set = symbols::border::Set {
                 top_left: symbols::line::NORMAL.horizontal_down,
@@ -124,7 +384,7 @@ impl App<CrosstermBackend<Stdout>> {
                 .title("VIP")
                 .borders(Borders::ALL ^ Borders::BOTTOM)
                 .border_set(border_set);
-            frame.render_widget(Paragraph::new(format!("{vip}")).block(block), split[0]);
+            frame.render_widget(Paragraph::new(vips).block(block), split[0]);
 
             (
                 split[1],
@@ -144,20 +404,10 @@ impl App<CrosstermBackend<Stdout>> {
             )
         };
 
-        let (control_server_area, border_set, borders) = if self.config.servers.worker.is_empty() {
-            (control_server_area, border_set, Borders::ALL)
-        } else {
+        let (control_server_area, border_set, borders) = {
             let split = Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)])
                 .split(control_server_area);
 
-            let worker: Vec<&str> = self
-                .config
-                .servers
-                .worker
-                .iter()
-                .map(|s| s.as_ref())
-                .collect();
-
             let border_set = symbols::border::Set {
                 top_left: symbols::line::NORMAL.vertical_right,
                 top_right: symbols::line::NORMAL.vertical_left,
@@ -165,83 +415,1248 @@ impl App<CrosstermBackend<Stdout>> {
             };
 
             let block = Block::new()
-                .title("Worker Nodes")
+                .title(self.node_title("Worker Nodes", Focus::Worker))
                 .borders(Borders::ALL)
+                .border_style(self.border_style(Focus::Worker))
                 .border_set(border_set);
 
-            let mut worker_state = ListState::default();
-            frame.render_stateful_widget(
-                List::new(worker).block(block),
-                split[1],
-                &mut worker_state,
-            );
+            let (worker, mut worker_state) = self.node_list(Focus::Worker);
+            frame.render_stateful_widget(worker.block(block), split[1], &mut worker_state);
 
             (split[0], border_set, (Borders::ALL ^ Borders::BOTTOM))
         };
 
-        let control: Vec<&str> = if self.config.servers.control.is_empty() {
-            vec!["No control plane nodes configured"]
-        } else {
-            self.config
-                .servers
-                .control
-                .iter()
-                .map(|s| s.as_ref())
-                .collect()
-        };
-
         let block = Block::new()
-            .title("Control Nodes")
+            .title(self.node_title("Control Nodes", Focus::Control))
             .borders(borders)
+            .border_style(self.border_style(Focus::Control))
             .border_set(border_set);
 
-        let mut control_state = ListState::default();
+        let (control, mut control_state) = self.node_list(Focus::Control);
         frame.render_stateful_widget(
-            List::new(control).block(block),
+            control.block(block),
             control_server_area,
             &mut control_state,
         );
+
+        self.draw_popup(frame);
     }
 
-    fn draw_logs(&self) -> List<'_> {
-        let logs = self
+    /// Single column layout without the cluster menu, for terminals too narrow to split
+    fn draw_narrow(&self, frame: &mut Frame, area: Rect) {
+        let vips = self.vip_lines();
+        let mut constraints = vec![];
+        if !vips.is_empty() {
+            constraints.push(Constraint::Length(vips.len() as u16 + 2));
+        }
+        constraints.extend([Constraint::Fill(1), Constraint::Fill(1)]);
+        if self.debug {
+            constraints.push(Constraint::Percentage(self.log_height));
+        }
+        let areas = Layout::vertical(constraints).split(area);
+        let mut areas = areas.iter().copied();
+
+        if !vips.is_empty() {
+            if let Some(area) = areas.next() {
+                let block = Block::bordered().title("VIP");
+                frame.render_widget(Paragraph::new(vips).block(block), area);
+            }
+        }
+
+        // The menu is hidden, so say which cluster this is
+        let title = match self.config.cluster_name() {
+            Some(name) => format!("Control Nodes ({name})"),
+            None => "Control Nodes".to_string(),
+        };
+        let panes = [
+            (title, Focus::Control),
+            ("Worker Nodes".to_string(), Focus::Worker),
+        ];
+        for ((title, pane), area) in panes.into_iter().zip(areas.by_ref()) {
+            let block = Block::bordered()
+                .title(self.node_title(&title, pane))
+                .border_style(self.border_style(pane));
+            let (list, mut state) = self.node_list(pane);
+            frame.render_stateful_widget(list.block(block), area, &mut state);
+        }
+
+        if let (true, Some(area)) = (self.debug, areas.next()) {
+            let mut state = ListState::default().with_offset(self.log_offset(area.height));
+            let block = Block::bordered().title("Tracing Logs");
+            frame.render_stateful_widget(self.draw_logs(block, area.width), area, &mut state);
+        }
+    }
+
+    fn nodes(&self, pane: Focus) -> &[Node] {
+        match pane {
+            Focus::Control => &self.config.servers().control,
+            Focus::Worker => &self.config.servers().worker,
+            Focus::Clusters | Focus::Logs => &[],
+        }
+    }
+
+    /// Nodes of a pane, narrowed to the matches while finding in it
+    fn node_list(&self, pane: Focus) -> (List<'_>, ListState) {
+        let servers = self.config.servers();
+        let (empty, bootstrap) = match pane {
+            Focus::Control => (
+                "No control plane nodes configured",
+                servers.primary_control(),
+            ),
+            _ => ("No worker nodes configured", None),
+        };
+
+        let nodes = self.nodes(pane);
+        let focused = self.focus == pane;
+        let (items, selected) = if focused && self.mode == InputMode::Search {
+            let matches = nodes
+                .iter()
+                .filter(|node| node_matches(node, &self.input))
+                .collect::<Vec<_>>();
+            let selected = (!matches.is_empty()).then_some(0);
+            (
+                node_items(matches, "No matching nodes", bootstrap),
+                selected,
+            )
+        } else {
+            let selected = self.selected_node.filter(|_| focused);
+            (node_items(nodes, empty, bootstrap), selected)
+        };
+
+        let list =
+            List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        (list, ListState::default().with_selected(selected))
+    }
+
+    /// Pane title, with the query while finding in it
+    fn node_title(&self, title: &str, pane: Focus) -> String {
+        if self.focus == pane && self.mode == InputMode::Search {
+            format!("{title} [find: {}]", self.input)
+        } else {
+            title.to_string()
+        }
+    }
+
+    /// One line per VIP, named once there are more than the API VIP
+    fn vip_lines(&self) -> Vec<Line<'_>> {
+        let servers = self.config.servers();
+        if servers.vips.is_empty() {
+            return servers
+                .vip
+                .iter()
+                .map(|vip| Line::from(vip.to_string()))
+                .collect();
+        }
+
+        servers
+            .vips()
+            .map(|(name, vip)| Line::from(format!("{name}: {vip}")))
+            .collect()
+    }
+
+    fn draw_popup(&self, frame: &mut Frame) {
+        match self.mode {
+            InputMode::Help => self.draw_help(frame),
+            InputMode::Palette => self.draw_palette(frame),
+            InputMode::Detail => self.draw_preview(frame),
+            _ => {}
+        }
+    }
+
+    /// Node picked in the focused pane, if any
+    fn selected(&self) -> Option<&Node> {
+        self.nodes(self.focus).get(self.selected_node?)
+    }
+
+    /// The `config.yaml` the selected node would get, straight from the generator
+    fn preview(&self) -> Option<String> {
+        Some(rke2::render_rke2_config(&self.config, self.selected()?))
+    }
+
+    fn draw_preview(&self, frame: &mut Frame) {
+        let (Some(node), Some(yaml)) = (self.selected(), self.preview()) else {
+            return;
+        };
+        let mut lines = yaml.lines().map(yaml_line).collect::<Vec<_>>();
+        if lines.is_empty() {
+            lines.push(Line::styled(
+                "Empty, RKE2's defaults apply",
+                Style::default().add_modifier(Modifier::ITALIC),
+            ));
+        }
+
+        let [area] = Layout::vertical([Constraint::Length(lines.len() as u16 + 2)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        let [area] = Layout::horizontal([Constraint::Percentage(80)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        let title = format!("config.yaml for {}", node.address);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            area,
+        );
+    }
+
+    fn draw_palette(&self, frame: &mut Frame) {
+        let actions = Action::matching(&self.input);
+        let items = actions
+            .iter()
+            .map(|action| ListItem::new(action.title()))
+            .collect::<Vec<_>>();
+
+        let [area] = Layout::vertical([Constraint::Length(Action::ALL.len() as u16 + 3)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        let [area] = Layout::horizontal([Constraint::Length(40)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        let block = Block::bordered().title("Command Palette");
+        let [input_area, list_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(block.inner(area));
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+        frame.render_widget(Paragraph::new(format!("> {}", self.input)), input_area);
+
+        let mut state = ListState::default().with_selected(Some(self.palette_selected));
+        frame.render_stateful_widget(
+            List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            list_area,
+            &mut state,
+        );
+    }
+
+    fn menu_items(&self) -> Vec<ListItem<'_>> {
+        let clusters = self.config.clusters();
+        let servers = self.config.servers();
+        if clusters.len() == 1 && servers.control.is_empty() && servers.worker.is_empty() {
+            return vec![empty_item("No nodes configured")];
+        }
+
+        let default = self.config.name.as_deref().unwrap_or("default");
+        if clusters.len() == 1 {
+            return vec![ListItem::new(default)];
+        }
+
+        // Number the clusters to match their `1`-`9` shortcuts
+        clusters
+            .iter()
+            .enumerate()
+            .map(|(index, cluster)| {
+                let servers = match cluster {
+                    Some(profile) => &self.config.profiles[*profile],
+                    None => &self.config.servers,
+                };
+                let total = servers.control.len() + servers.worker.len();
+                let enabled = servers.deployable().count();
+                ListItem::new(format!(
+                    "{} {} {enabled}/{total}",
+                    index + 1,
+                    cluster.unwrap_or(default)
+                ))
+            })
+            .collect()
+    }
+
+    fn selected_cluster(&self) -> Option<usize> {
+        let profile = self.config.profile();
+        self.config
+            .clusters()
+            .iter()
+            .position(|cluster| *cluster == profile)
+    }
+
+    /// Switch to the cluster at `index` in the menu, ignoring out of range indices
+    fn select_cluster(&mut self, index: usize) {
+        let clusters = self.config.clusters();
+        let Some(cluster) = clusters.get(index) else {
+            return;
+        };
+
+        match cluster.map(Box::<str>::from) {
+            Some(profile) => {
+                // Only listed profiles can be picked, so this can't be unknown
+                self.config.select_profile(&profile).ok();
+            }
+            None => self.config.select_top_level(),
+        }
+        self.selected_node = None;
+        if let Some(metrics) = &self.metrics {
+            metrics.set_nodes(self.config.servers());
+        }
+    }
+
+    /// Single line overview of the cluster for the compact view
+    fn summary(&self) -> String {
+        let servers = self.config.servers();
+        let total = servers.control.len() + servers.worker.len();
+        let enabled = servers.deployable().count();
+        let errors = self
             .logs
             .iter()
-            .map(|s| s.into())
+            .filter(|log| log.level() == Level::ERROR)
+            .count();
+
+        let mut summary = format!(
+            "{} | {enabled}/{total} nodes enabled | {errors} errors",
+            self.config.cluster_name().unwrap_or("ez-rke")
+        );
+        if let Some(vip) = &servers.vip {
+            summary.push_str(&format!(" | VIP {vip}"));
+        }
+
+        summary
+    }
+
+    fn draw_help(&self, frame: &mut Frame) {
+        let width = HELP
+            .iter()
+            .map(|(key, _)| key.len())
+            .max()
+            .unwrap_or_default();
+        let lines = HELP
+            .iter()
+            .map(|(key, action)| Line::from(format!("{key:width$}  {action}")))
+            .collect::<Vec<_>>();
+
+        let [area] = Layout::vertical([Constraint::Length(lines.len() as u16 + 2)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        let [area] = Layout::horizontal([Constraint::Length(40)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Help")),
+            area,
+        );
+    }
+
+    /// Follow the newest log, which sits at the bottom normally and at the top when reversed
+    fn log_offset(&self, height: u16) -> usize {
+        if self.reverse_logs {
+            0
+        } else {
+            let len = self.logs.len();
+            len.saturating_sub((height as usize).saturating_sub(1))
+        }
+    }
+
+    fn draw_logs<'a>(&'a self, block: Block<'a>, width: u16) -> List<'a> {
+        let format = LogFormat {
+            scope_width: log::scope_width(width),
+            ..self.log_format
+        };
+
+        let mut logs = self
+            .logs
+            .iter()
+            .map(|log| log.to_list_item(&format))
             .collect::<Vec<ListItem>>();
 
-        List::new(logs).block(Block::new().borders(Borders::ALL ^ Borders::TOP))
+        if self.reverse_logs {
+            logs.reverse();
+        }
+        if logs.is_empty() {
+            logs.push(empty_item("Waiting for logs…"));
+        }
+
+        List::new(logs).block(
+            block
+                .border_style(self.border_style(Focus::Logs))
+                .title_bottom(log::legend().right_aligned()),
+        )
     }
 
-    async fn handle_events(&mut self) {
+    fn border_style(&self, pane: Focus) -> Style {
+        if self.focus == pane {
+            focused_border_style()
+        } else {
+            Style::default()
+        }
+    }
+
+    /// Fails once input can no longer be read, ending the UI
+    async fn handle_events(&mut self) -> io::Result<()> {
         match self.events.next().await {
             crate::event::Event::Tick => {}
             crate::event::Event::Key(key) => self.handle_key_events(key),
             crate::event::Event::Mouse(_) => {}
-            crate::event::Event::Resize(_, _) => {}
-            crate::event::Event::Log(log) => self.logs.push(log),
+            crate::event::Event::Resize(width, _) => self.resize(width),
+            crate::event::Event::Paste(text) => self.handle_paste(&text),
+            crate::event::Event::Log(log) => self.handle_log(log),
+            crate::event::Event::Fatal(message) => return Err(io::Error::other(message)),
             crate::event::Event::Invalid => {}
         }
+
+        Ok(())
+    }
+
+    /// Switch layouts with the terminal width, moving focus off the menu once it's hidden
+    fn resize(&mut self, width: u16) {
+        self.narrow = width < NARROW_WIDTH;
+        if self.narrow && self.focus == Focus::Clusters {
+            self.focus = default_focus(self.narrow);
+        }
+    }
+
+    fn handle_log(&mut self, log: LogEvent) {
+        if self.config.ui.bell_on_error && log.level() == Level::ERROR {
+            let now = Instant::now();
+            if should_ring(self.last_bell, now) {
+                execute!(io::stdout(), Print('\x07')).ok();
+                self.last_bell = Some(now);
+            }
+        }
+
+        // Events can arrive slightly out of order, keep the pane in emit order
+        let index = self
+            .logs
+            .partition_point(|other| other.instant() <= log.instant());
+        self.logs.insert(index, log);
+    }
+
+    /// Pasted text goes into whichever input is open, the inputs are single line
+    fn handle_paste(&mut self, text: &str) {
+        let text = text.lines().collect::<Vec<_>>().join(" ");
+        match self.mode {
+            InputMode::Search => self.input.push_str(&text),
+            InputMode::Palette => {
+                self.input.push_str(&text);
+                self.palette_selected = 0;
+            }
+            _ => {}
+        }
     }
 
     pub fn handle_key_events(&mut self, key_event: KeyEvent) {
         debug!(?key_event);
-        match key_event.code {
-            // Exit application on `ESC` or `q`
-            KeyCode::Esc | KeyCode::Char('q') => {
+
+        // Exit application on `Ctrl-C` regardless of mode
+        if key_event.modifiers == KeyModifiers::CONTROL
+            && matches!(key_event.code, KeyCode::Char('c') | KeyCode::Char('C'))
+        {
+            self.running = false;
+            return;
+        }
+
+        match self.mode {
+            InputMode::Normal => self.handle_normal_key(key_event),
+            InputMode::Search => self.handle_search_key(key_event),
+            InputMode::Confirm => self.handle_confirm_key(key_event),
+            InputMode::Detail | InputMode::Help => self.handle_popup_key(key_event),
+            InputMode::Palette => self.handle_palette_key(key_event),
+        }
+    }
+
+    fn dispatch(&mut self, action: Action) {
+        debug!(?action);
+        match action {
+            Action::Quit => {
                 self.running = false;
             }
-            // Exit application on `Ctrl-C`
-            KeyCode::Char('c') | KeyCode::Char('C') => {
-                if key_event.modifiers == KeyModifiers::CONTROL {
-                    self.running = false;
+            Action::ToggleDebug => {
+                self.debug = !self.debug;
+                if !self.debug && self.focus == Focus::Logs {
+                    self.focus = default_focus(self.narrow);
                 }
             }
-            KeyCode::Char('d') | KeyCode::Char('D') => {
-                self.debug = !self.debug;
+            Action::ReverseLogs => {
+                self.reverse_logs = !self.reverse_logs;
+            }
+            Action::ToggleTimestamps => {
+                self.log_format.timestamps = !self.log_format.timestamps;
+            }
+            Action::ClearLogs => {
+                // Only the pane, the log file keeps everything
+                self.logs.clear();
+            }
+            Action::GrowLogs => {
+                self.log_height = clamp_log_height(self.log_height + LOG_HEIGHT_STEP);
+            }
+            Action::ShrinkLogs => {
+                self.log_height = clamp_log_height(self.log_height.saturating_sub(LOG_HEIGHT_STEP));
+            }
+            Action::ToggleCompact => {
+                self.compact = !self.compact;
+            }
+            Action::FocusNext => {
+                self.focus = self.focus.cycle(true, !self.narrow, self.debug);
+                self.selected_node = None;
+            }
+            Action::FocusPrevious => {
+                self.focus = self.focus.cycle(false, !self.narrow, self.debug);
+                self.selected_node = None;
+            }
+            Action::FindNode => {
+                // Finding only makes sense in a node pane
+                if !matches!(self.focus, Focus::Control | Focus::Worker) {
+                    self.focus = Focus::Control;
+                    self.selected_node = None;
+                }
+                self.input.clear();
+                self.mode = InputMode::Search;
+            }
+            Action::PreviewConfig => {
+                // Read only, so a node only needs picking first
+                if self.selected().is_some() {
+                    self.mode = InputMode::Detail;
+                }
+            }
+            Action::Help => {
+                self.mode = InputMode::Help;
+            }
+        }
+    }
+
+    fn handle_normal_key(&mut self, key_event: KeyEvent) {
+        let action = match key_event.code {
+            KeyCode::Char('p') | KeyCode::Char('P')
+                if key_event.modifiers == KeyModifiers::CONTROL =>
+            {
+                self.input.clear();
+                self.palette_selected = 0;
+                self.mode = InputMode::Palette;
+                return;
+            }
+            // Exit application on `ESC` or `q`
+            KeyCode::Esc | KeyCode::Char('q') => Action::Quit,
+            KeyCode::Char('d') | KeyCode::Char('D') => Action::ToggleDebug,
+            KeyCode::Char('r') | KeyCode::Char('R') => Action::ReverseLogs,
+            KeyCode::Char('T') => Action::ToggleTimestamps,
+            KeyCode::Backspace => Action::ClearLogs,
+            KeyCode::Char('+') if self.debug => Action::GrowLogs,
+            KeyCode::Char('-') if self.debug => Action::ShrinkLogs,
+            KeyCode::Char('c') | KeyCode::Char('C') => Action::ToggleCompact,
+            KeyCode::Char('?') => Action::Help,
+            KeyCode::Char('f') => Action::FindNode,
+            KeyCode::Enter => Action::PreviewConfig,
+            KeyCode::Tab => Action::FocusNext,
+            KeyCode::BackTab => Action::FocusPrevious,
+            // Jump straight to one of the first nine clusters
+            KeyCode::Char(digit @ '1'..='9') => {
+                self.select_cluster(digit as usize - '1' as usize);
+                return;
             }
             // Other handlers you could add here.
+            _ => return,
+        };
+
+        self.dispatch(action);
+    }
+
+    fn handle_palette_key(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.input.clear();
+                self.mode = InputMode::Normal;
+            }
+            KeyCode::Enter => {
+                let action = Action::matching(&self.input)
+                    .get(self.palette_selected)
+                    .copied();
+                self.input.clear();
+                self.mode = InputMode::Normal;
+                if let Some(action) = action {
+                    self.dispatch(action);
+                }
+            }
+            KeyCode::Up => {
+                self.palette_selected = self.palette_selected.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                let last = Action::matching(&self.input).len().saturating_sub(1);
+                self.palette_selected = (self.palette_selected + 1).min(last);
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.palette_selected = 0;
+            }
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                self.palette_selected = 0;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_search_key(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.input.clear();
+                self.mode = InputMode::Normal;
+            }
+            KeyCode::Enter => {
+                // Jump to the first match, keeping the current selection if nothing matched
+                let first = self
+                    .nodes(self.focus)
+                    .iter()
+                    .position(|node| node_matches(node, &self.input));
+                self.selected_node = first.or(self.selected_node);
+                self.input.clear();
+                self.mode = InputMode::Normal;
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Char(c) => {
+                self.input.push(c);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_confirm_key(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('n') => {
+                self.mode = InputMode::Normal;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_popup_key(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('?') => {
+                self.mode = InputMode::Normal;
+            }
             _ => {}
         }
     }
 }
+
+/// Drop foreground and background colours but keep modifiers, so highlights stay readable
+fn strip_colors(buffer: &mut Buffer) {
+    for cell in buffer.content.iter_mut() {
+        cell.set_fg(Color::Reset).set_bg(Color::Reset);
+    }
+}
+
+/// Swap box-drawing characters for ASCII. Works on the finished frame so every custom border
+/// set and junction in `draw` is covered without duplicating them.
+fn ascii_borders(buffer: &mut Buffer) {
+    for cell in buffer.content.iter_mut() {
+        let ascii = match cell.symbol() {
+            "─" | "━" | "═" => "-",
+            "│" | "┃" | "║" => "|",
+            "┌" | "┐" | "└" | "┘" | "├" | "┤" | "┬" | "┴" | "┼" => "+",
+            "╭" | "╮" | "╰" | "╯" => "+",
+            _ => continue,
+        };
+        cell.set_symbol(ascii);
+    }
+}
+
+/// Items for a node pane, `bootstrap` being the node to badge as the cluster's first server
+fn node_items<'a>(
+    nodes: impl IntoIterator<Item = &'a Node>,
+    empty: &'a str,
+    bootstrap: Option<&Node>,
+) -> Vec<ListItem<'a>> {
+    let items = nodes
+        .into_iter()
+        .map(|node| {
+            let is_bootstrap = bootstrap.is_some_and(|bootstrap| bootstrap.address == node.address);
+            node_item(node, is_bootstrap)
+        })
+        .collect::<Vec<_>>();
+
+    if items.is_empty() {
+        vec![empty_item(empty)]
+    } else {
+        items
+    }
+}
+
+/// Case-insensitive substring match on the node address
+fn node_matches(node: &Node, query: &str) -> bool {
+    node.address
+        .to_lowercase()
+        .contains(&query.trim().to_lowercase())
+}
+
+/// Disabled nodes stay listed but are dimmed so they read as parked
+fn node_item(node: &Node, bootstrap: bool) -> ListItem<'_> {
+    let mut line = Line::from(node.address.as_ref());
+    if bootstrap {
+        line.push_span(Span::styled(
+            " [bootstrap]",
+            Style::default().fg(Color::Magenta),
+        ));
+    }
+    let item = ListItem::new(line);
+
+    if node.enabled {
+        item
+    } else {
+        item.style(
+            Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::DIM),
+        )
+    }
+}
+
+/// The cluster menu, or the control pane when the narrow layout hides the menu
+fn default_focus(narrow: bool) -> Focus {
+    if narrow {
+        Focus::Control
+    } else {
+        Focus::default()
+    }
+}
+
+/// Border of whichever pane has focus
+fn focused_border_style() -> Style {
+    Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::BOLD)
+}
+
+/// Set the terminal up with `init`, calling `restore` when that fails as raw mode may already
+/// be on if only the alternate screen failed
+fn init_terminal<T>(init: impl FnOnce() -> io::Result<T>, restore: impl FnOnce()) -> io::Result<T> {
+    init().inspect_err(|_| restore())
+}
+
+fn should_ring(last_bell: Option<Instant>, now: Instant) -> bool {
+    last_bell.is_none_or(|last| now.duration_since(last) >= BELL_DEBOUNCE)
+}
+
+/// Highlight a line of generated YAML, keys apart from their values and list items
+fn yaml_line(line: &str) -> Line<'_> {
+    let key = Style::default().fg(Color::Cyan);
+    let value = Style::default().fg(Color::Green);
+
+    if let Some(item) = line.strip_prefix("  - ") {
+        Line::from(vec![Span::raw("  - "), Span::styled(item, value)])
+    } else if let Some((name, rest)) = line.split_once(':') {
+        Line::from(vec![
+            Span::styled(name, key),
+            Span::raw(":"),
+            Span::styled(rest, value),
+        ])
+    } else {
+        Line::from(line)
+    }
+}
+
+/// Guidance shown in place of an empty list
+fn empty_item(text: &str) -> ListItem<'_> {
+    ListItem::new(text).style(Style::default().add_modifier(Modifier::ITALIC))
+}
+
+#[cfg(unix)]
+fn is_privileged() -> bool {
+    // SAFETY: geteuid has no preconditions and cannot fail
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_privileged() -> bool {
+    true
+}
+
+fn clamp_log_height(height: u16) -> u16 {
+    height.clamp(LOG_HEIGHT_RANGE.0, LOG_HEIGHT_RANGE.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+
+    fn app(width: u16, height: u16, config: &str) -> App<TestBackend> {
+        let terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        let config = toml::from_str(config).unwrap();
+        let mut app = App::with_terminal(terminal, EventHandler::detached(), config);
+        // Keep the root banner out of the way whoever runs the tests
+        app.privileged = true;
+
+        app
+    }
+
+    /// Draw a frame and return it row by row
+    fn render(app: &App<TestBackend>) -> Vec<String> {
+        let mut terminal = app.terminal.try_lock().unwrap();
+        terminal.draw(|frame| app.render(frame)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn row_of(rows: &[String], text: &str) -> usize {
+        rows.iter()
+            .position(|row| row.contains(text))
+            .unwrap_or_else(|| panic!("`{text}` not rendered"))
+    }
+
+    const CONFIG: &str = r#"
+        [servers]
+        control = ["10.0.0.1"]
+        worker = ["10.0.0.2"]
+    "#;
+
+    #[test]
+    fn reversed_logs_render_newest_first() {
+        let logs = ["first", "second", "third"]
+            .map(|message| LogEvent::new(Level::INFO, "ez_rke", message))
+            .to_vec();
+        let mut app = app(100, 30, CONFIG).with_logs(logs);
+        app.dispatch(Action::ToggleDebug);
+
+        let rows = render(&app);
+        let order = ["first", "second", "third"].map(|message| row_of(&rows, message));
+        assert!(order[0] < order[1] && order[1] < order[2]);
+
+        app.dispatch(Action::ReverseLogs);
+        let rows = render(&app);
+        let order = ["first", "second", "third"].map(|message| row_of(&rows, message));
+        assert!(order[0] > order[1] && order[1] > order[2]);
+    }
+
+    #[test]
+    fn config_warnings_open_the_log_pane() {
+        let app = app(100, 30, CONFIG);
+        assert!(!render(&app).iter().any(|row| row.contains("Tracing Logs")));
+
+        let app = app.with_warnings(vec!["VIP is off subnet".to_string()]);
+        let rows = render(&app);
+        row_of(&rows, "Tracing Logs");
+        row_of(&rows, "VIP is off subnet");
+    }
+
+    #[test]
+    fn narrow_terminal_hides_the_menu() {
+        let app = app(40, 30, CONFIG);
+
+        let rows = render(&app);
+        assert!(!rows.iter().any(|row| row.contains("Configuration")));
+        row_of(&rows, "10.0.0.1");
+        row_of(&rows, "10.0.0.2");
+        assert_eq!(app.focus, Focus::Control);
+    }
+
+    #[test]
+    fn focus_skips_hidden_panes() {
+        let mut app = app(40, 30, CONFIG);
+        let mut panes = vec![];
+        for _ in 0..3 {
+            app.dispatch(Action::FocusNext);
+            panes.push(app.focus);
+        }
+        assert_eq!(panes, [Focus::Worker, Focus::Control, Focus::Worker]);
+
+        app.resize(120);
+        app.dispatch(Action::FocusNext);
+        assert_eq!(app.focus, Focus::Clusters);
+
+        app.resize(40);
+        assert_eq!(app.focus, Focus::Control);
+    }
+
+    #[test]
+    fn switching_clusters_updates_the_metrics() {
+        let metrics = Arc::new(Metrics::default());
+        let config = r#"
+            [profiles.dev]
+            control = ["10.0.0.1"]
+
+            [profiles.prod]
+            control = ["10.0.1.1", "10.0.1.2"]
+            worker = ["10.0.1.3"]
+        "#;
+        let mut app = app(100, 30, config).with_metrics(Some(metrics.clone()));
+
+        app.select_cluster(0);
+        assert!(metrics
+            .render()
+            .contains("ez_rke_nodes{state=\"total\"} 1\n"));
+
+        app.select_cluster(1);
+        assert!(metrics
+            .render()
+            .contains("ez_rke_nodes{state=\"total\"} 3\n"));
+    }
+
+    #[test]
+    fn log_offset_follows_the_newest_log() {
+        let logs = (0..10)
+            .map(|index| LogEvent::new(Level::INFO, "ez_rke", &index.to_string()))
+            .collect();
+        let mut app = app(100, 30, CONFIG).with_logs(logs);
+
+        assert_eq!(app.log_offset(5), 6);
+        assert_eq!(app.log_offset(20), 0);
+
+        app.reverse_logs = true;
+        assert_eq!(app.log_offset(5), 0);
+    }
+
+    #[test]
+    fn title_names_the_cluster() {
+        assert_eq!(app(100, 30, CONFIG).title(), "ez-rke");
+
+        let named = app(100, 30, &format!("name = \"prod\"\n{CONFIG}"));
+        assert_eq!(named.title(), "ez-rke: prod");
+    }
+
+    #[test]
+    fn disabled_nodes_stay_listed_but_are_not_deployed() {
+        let app = app(
+            100,
+            30,
+            r#"
+            [servers]
+            control = ["10.0.0.1"]
+            worker = [{ address = "10.0.0.2", enabled = false }]
+            "#,
+        );
+
+        row_of(&render(&app), "10.0.0.2");
+        let deployable = app
+            .config
+            .servers()
+            .deployable()
+            .map(|node| node.address.as_ref())
+            .collect::<Vec<_>>();
+        assert_eq!(deployable, ["10.0.0.1"]);
+    }
+
+    #[test]
+    fn bell_is_debounced() {
+        let start = Instant::now();
+
+        assert!(should_ring(None, start));
+        assert!(!should_ring(Some(start), start));
+        assert!(!should_ring(Some(start), start + BELL_DEBOUNCE / 2));
+        assert!(should_ring(Some(start), start + BELL_DEBOUNCE));
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn keys_depend_on_the_input_mode() {
+        let mut app = app(100, 30, CONFIG);
+        app.running = true;
+
+        app.handle_key_events(key(KeyCode::Char('f')));
+        assert_eq!(app.mode, InputMode::Search);
+        app.handle_key_events(key(KeyCode::Char('q')));
+        assert_eq!(app.input, "q");
+        assert!(app.running);
+
+        app.handle_key_events(key(KeyCode::Esc));
+        assert_eq!(app.mode, InputMode::Normal);
+        app.handle_key_events(key(KeyCode::Char('q')));
+        assert!(!app.running);
+    }
+
+    #[test]
+    fn summary_line_counts_nodes_and_errors() {
+        let config = r#"
+            name = "prod"
+
+            [servers]
+            control = ["10.0.0.1"]
+            worker = ["10.0.0.2", { address = "10.0.0.3", enabled = false }]
+            vip = "10.0.0.100"
+        "#;
+        let logs = vec![
+            LogEvent::new(Level::ERROR, "ez_rke", "join failed"),
+            LogEvent::new(Level::INFO, "ez_rke", "retrying"),
+        ];
+        let app = app(100, 30, config).with_logs(logs);
+
+        assert_eq!(
+            app.summary(),
+            "prod | 2/3 nodes enabled | 1 errors | VIP 10.0.0.100"
+        );
+    }
+
+    #[test]
+    fn empty_panes_show_guidance() {
+        let app = app(100, 30, "[servers]\ncontrol = [\"10.0.0.1\"]\n");
+
+        row_of(&render(&app), "No worker nodes configured");
+    }
+
+    #[test]
+    fn logs_keep_emit_order() {
+        let first = LogEvent::new(Level::INFO, "ez_rke", "first");
+        std::thread::sleep(Duration::from_millis(1));
+        let second = LogEvent::new(Level::INFO, "ez_rke", "second");
+        let mut app = app(100, 30, CONFIG);
+
+        app.handle_log(second);
+        app.handle_log(first);
+        app.dispatch(Action::ToggleDebug);
+
+        let rows = render(&app);
+        assert!(row_of(&rows, "first") < row_of(&rows, "second"));
+    }
+
+    #[test]
+    fn banner_only_without_root() {
+        let banner = "Not running as root";
+        let mut app = app(100, 30, CONFIG);
+        assert!(!render(&app).iter().any(|row| row.contains(banner)));
+
+        app.privileged = false;
+        assert_eq!(row_of(&render(&app), banner), 0);
+    }
+
+    #[test]
+    fn digits_select_clusters_in_menu_order() {
+        let config = r#"
+            [servers]
+            control = ["10.0.0.1"]
+
+            [profiles.dev]
+            control = ["10.0.1.1"]
+
+            [profiles.prod]
+            control = ["10.0.2.1"]
+        "#;
+        let mut app = app(100, 30, config);
+
+        app.handle_key_events(key(KeyCode::Char('3')));
+        assert_eq!(app.config.profile(), Some("prod"));
+        app.handle_key_events(key(KeyCode::Char('1')));
+        assert_eq!(app.config.profile(), None);
+        app.handle_key_events(key(KeyCode::Char('2')));
+        assert_eq!(app.config.profile(), Some("dev"));
+
+        // Out of range leaves the selection alone
+        app.handle_key_events(key(KeyCode::Char('9')));
+        assert_eq!(app.selected_cluster(), Some(1));
+    }
+
+    #[test]
+    fn log_height_is_clamped_and_splits_the_screen() {
+        assert_eq!(clamp_log_height(0), 10);
+        assert_eq!(clamp_log_height(50), 50);
+        assert_eq!(clamp_log_height(95), 90);
+
+        let mut app = app(100, 40, &format!("ui = {{ log_height = 50 }}\n{CONFIG}"));
+        app.dispatch(Action::ToggleDebug);
+        let half = row_of(&render(&app), "Tracing Logs");
+        assert!((19..=20).contains(&half), "{half}");
+
+        // 5% more of 40 rows moves the pane up by two
+        app.dispatch(Action::GrowLogs);
+        assert_eq!(app.log_height, 55);
+        assert_eq!(row_of(&render(&app), "Tracing Logs"), half - 2);
+
+        app.log_height = 90;
+        app.dispatch(Action::GrowLogs);
+        assert_eq!(app.log_height, 90);
+    }
+
+    #[test]
+    fn empty_log_pane_shows_a_placeholder() {
+        let mut app = app(100, 30, CONFIG);
+        app.dispatch(Action::ToggleDebug);
+
+        row_of(&render(&app), "Waiting for logs…");
+    }
+
+    #[test]
+    fn focused_pane_border_is_highlighted() {
+        let mut app = app(100, 30, CONFIG);
+        app.focus = Focus::Control;
+
+        // Style of the border cell just left of a pane title
+        let border = |app: &App<TestBackend>, title: &str| {
+            let rows = render(app);
+            let y = row_of(&rows, title);
+            let x = rows[y].chars().take(rows[y].find(title).unwrap()).count() - 1;
+            let terminal = app.terminal.try_lock().unwrap();
+            terminal.backend().buffer()[(x as u16, y as u16)].style()
+        };
+
+        let highlight = focused_border_style();
+        assert_eq!(border(&app, "Control Nodes").fg, highlight.fg);
+        assert!(border(&app, "Control Nodes")
+            .add_modifier
+            .contains(Modifier::BOLD));
+        assert_ne!(border(&app, "Worker Nodes").fg, highlight.fg);
+
+        app.dispatch(Action::FocusNext);
+        assert_eq!(border(&app, "Worker Nodes").fg, highlight.fg);
+        assert_ne!(border(&app, "Control Nodes").fg, highlight.fg);
+    }
+
+    #[test]
+    fn menu_counts_enabled_nodes_per_cluster() {
+        let config = r#"
+            [servers]
+            control = ["10.0.0.1"]
+            worker = ["10.0.0.2"]
+
+            [profiles.staging]
+            control = ["10.0.1.1", { address = "10.0.1.2", enabled = false }]
+            worker = ["10.0.1.3"]
+        "#;
+        let app = app(100, 30, config);
+
+        let rows = render(&app);
+        row_of(&rows, "1 default 2/2");
+        row_of(&rows, "2 staging 2/3");
+    }
+
+    #[test]
+    fn clearing_logs_empties_the_pane_and_resets_the_offset() {
+        let logs = (0..20)
+            .map(|i| LogEvent::new(Level::INFO, "ez_rke", &format!("log {i}")))
+            .collect();
+        let mut app = app(100, 30, CONFIG).with_logs(logs);
+        assert_eq!(app.log_offset(5), 16);
+
+        app.handle_key_events(key(KeyCode::Backspace));
+        assert!(app.logs.is_empty());
+        assert_eq!(app.log_offset(5), 0);
+    }
+
+    #[test]
+    fn paste_goes_into_the_open_input_only() {
+        let mut app = app(100, 30, CONFIG);
+
+        app.handle_paste("10.0.0");
+        assert_eq!(app.input, "");
+
+        app.handle_key_events(key(KeyCode::Char('f')));
+        app.handle_paste("10.0\n.0.2");
+        assert_eq!(app.input, "10.0 .0.2");
+
+        app.handle_key_events(key(KeyCode::Esc));
+        app.handle_key_events(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL));
+        app.palette_selected = 2;
+        app.handle_paste("clear");
+        assert_eq!(app.input, "clear");
+        assert_eq!(app.palette_selected, 0);
+    }
+
+    #[test]
+    fn find_jumps_to_the_first_matching_node() {
+        let node =
+            |address: &str| toml::from_str::<Node>(&format!("address = {address:?}")).unwrap();
+        assert!(node_matches(&node("Node-A.example.com"), " node-a "));
+        assert!(node_matches(&node("10.0.0.12"), ""));
+        assert!(!node_matches(&node("10.0.0.12"), "10.1"));
+
+        let config = r#"
+            [servers]
+            control = ["10.0.0.1"]
+            worker = ["10.0.0.2", "10.0.1.3", "10.0.1.4"]
+        "#;
+        let mut app = app(100, 30, config);
+        app.focus = Focus::Worker;
+        let mut find = |query: &str| {
+            app.handle_key_events(key(KeyCode::Char('f')));
+            for c in query.chars() {
+                app.handle_key_events(key(KeyCode::Char(c)));
+            }
+            app.handle_key_events(key(KeyCode::Enter));
+            app.selected_node
+        };
+
+        assert_eq!(find("0.1."), Some(1));
+        // No match keeps the selection
+        assert_eq!(find("10.9"), Some(1));
+        assert_eq!(find(".4"), Some(2));
+    }
+
+    #[test]
+    fn ascii_mode_draws_only_ascii() {
+        let config = r#"
+            [servers]
+            control = ["10.0.0.1"]
+            worker = ["10.0.0.2"]
+            vip = "10.0.0.100"
+
+            [profiles.staging]
+            control = ["10.0.1.1"]
+        "#;
+        let mut app = app(100, 30, config).with_ascii_borders(true);
+        app.dispatch(Action::ToggleDebug);
+
+        // Box drawing characters, text like the log placeholder may still be Unicode
+        let box_drawing = |rows: &[String]| {
+            rows.iter()
+                .any(|row| row.chars().any(|c| ('\u{2500}'..='\u{257f}').contains(&c)))
+        };
+
+        let rows = render(&app);
+        assert!(!box_drawing(&rows), "{rows:#?}");
+        row_of(&rows, "+Control Nodes---");
+        row_of(&rows, "|10.0.0.1");
+
+        app.ascii_borders = false;
+        assert!(box_drawing(&render(&app)));
+    }
+
+    #[test]
+    fn preview_shows_the_generated_config() {
+        let config = r#"
+            [servers]
+            control = ["10.0.0.1"]
+            worker = ["10.0.0.2", { address = "10.0.0.3", node_ip = "192.168.0.3" }]
+            tls_san = ["k8s.example.com"]
+        "#;
+        let mut app = app(100, 30, config);
+
+        // Nothing picked yet, so there is nothing to preview
+        app.handle_key_events(key(KeyCode::Enter));
+        assert_eq!(app.mode, InputMode::Normal);
+
+        app.focus = Focus::Worker;
+        app.handle_key_events(key(KeyCode::Char('f')));
+        app.handle_key_events(key(KeyCode::Char('3')));
+        app.handle_key_events(key(KeyCode::Enter));
+        app.handle_key_events(key(KeyCode::Enter));
+        assert_eq!(app.mode, InputMode::Detail);
+
+        let node = &app.config.servers().worker[1];
+        let expected = rke2::render_rke2_config(&app.config, node);
+        assert_eq!(app.preview().as_deref(), Some(expected.as_str()));
+
+        let rows = render(&app);
+        let title = row_of(&rows, "config.yaml for 10.0.0.3");
+        for (offset, line) in expected.lines().enumerate() {
+            assert_eq!(row_of(&rows, line), title + 1 + offset, "{line}");
+        }
+
+        app.handle_key_events(key(KeyCode::Esc));
+        assert_eq!(app.mode, InputMode::Normal);
+    }
+
+    #[test]
+    fn terminal_setup_failure_is_an_error() {
+        let mut restored = false;
+        let result = init_terminal(
+            || Err::<(), _>(io::Error::other("not a terminal")),
+            || restored = true,
+        );
+        assert_eq!(result.unwrap_err().to_string(), "not a terminal");
+        assert!(restored);
+
+        let mut restored = false;
+        assert!(init_terminal(|| Ok(()), || restored = true).is_ok());
+        assert!(!restored);
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    env,
+    fmt::Display,
+    fs::OpenOptions,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::config::{Config, ConfigError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+    /// Something is missing but only some features need it
+    Warn,
+    Fail,
+}
+
+/// Outcome of a single environment check
+#[derive(Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+impl Display for Check {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self.status {
+            Status::Pass => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+        };
+
+        write!(f, "[{status:^4}] {}: {}", self.name, self.detail)
+    }
+}
+
+/// `program` should be on the `PATH`, only a `required` one fails the check when missing
+pub fn program(program: &'static str, required: bool) -> Check {
+    match find_program(program) {
+        Some(path) => Check::new(program, Status::Pass, path.display().to_string()),
+        None if required => Check::new(program, Status::Fail, "not found on PATH"),
+        None => Check::new(program, Status::Warn, "not found on PATH"),
+    }
+}
+
+fn find_program(program: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+pub fn config(result: &Result<Config, ConfigError>) -> Check {
+    match result {
+        Ok(config) => {
+            let warnings = config.warnings();
+            if warnings.is_empty() {
+                Check::new("config", Status::Pass, "valid")
+            } else {
+                Check::new("config", Status::Warn, warnings.join("; "))
+            }
+        }
+        Err(err) => Check::new("config", Status::Fail, err.to_string()),
+    }
+}
+
+/// The log file is opened the same way `init_logger` does, so this creates it if missing
+pub fn log_file(path: &Path) -> Check {
+    match OpenOptions::new().append(true).create(true).open(path) {
+        Ok(_) => Check::new("log file", Status::Pass, path.display().to_string()),
+        Err(err) => Check::new(
+            "log file",
+            Status::Fail,
+            format!("{}: {err}", path.display()),
+        ),
+    }
+}
+
+/// Print every check, returning whether none of them failed
+pub fn report(checks: &[Check], mut out: impl Write) -> io::Result<bool> {
+    for check in checks {
+        writeln!(out, "{check}")?;
+    }
+    out.flush()?;
+
+    Ok(checks.iter().all(|check| check.status != Status::Fail))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report_of(checks: &[Check]) -> (String, bool) {
+        let mut out = vec![];
+        let healthy = report(checks, &mut out).unwrap();
+
+        (String::from_utf8(out).unwrap(), healthy)
+    }
+
+    #[test]
+    fn report_fails_only_on_hard_failures() {
+        let mut checks = vec![
+            Check::new("ssh", Status::Pass, "/usr/bin/ssh"),
+            Check::new("kubectl", Status::Warn, "not found on PATH"),
+        ];
+
+        let (out, healthy) = report_of(&checks);
+        assert_eq!(
+            out,
+            "[ ok ] ssh: /usr/bin/ssh\n[warn] kubectl: not found on PATH\n"
+        );
+        assert!(healthy);
+
+        checks.push(Check::new("config", Status::Fail, "no control nodes"));
+        let (out, healthy) = report_of(&checks);
+        assert!(out.ends_with("[FAIL] config: no control nodes\n"));
+        assert!(!healthy);
+    }
+
+    #[test]
+    fn config_check_follows_errors_and_warnings() {
+        let parse = |toml: &str| Ok(toml::from_str::<Config>(toml).unwrap());
+
+        let clean = parse(
+            r#"
+            [servers]
+            control = ["10.0.0.1"]
+            vip = "10.0.0.100"
+            "#,
+        );
+        assert_eq!(config(&clean).status, Status::Pass);
+
+        let off_subnet = parse(
+            r#"
+            [servers]
+            control = ["10.0.0.1"]
+            vip = "192.168.1.100"
+            "#,
+        );
+        let check = config(&off_subnet);
+        assert_eq!(check.status, Status::Warn);
+        assert!(check.detail.contains("192.168.1.100"));
+
+        let check = config(&Err(ConfigError::NoControlNodes(None)));
+        assert_eq!(check.status, Status::Fail);
+        assert_eq!(check.detail, "No control nodes configured");
+    }
+}
@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{any::Any, future::Future, time::Duration};
 
 use futures::{FutureExt, StreamExt};
 use ratatui::crossterm::event::{KeyEvent, MouseEvent};
@@ -10,7 +10,10 @@ pub enum Event {
     Key(KeyEvent),
     Mouse(MouseEvent),
     Resize(u16, u16),
+    Paste(String),
     Log(LogEvent),
+    /// The terminal reader died and no more input will arrive
+    Fatal(String),
     Invalid,
 }
 
@@ -24,42 +27,15 @@ impl EventHandler {
     pub fn new(tick_rate: Duration) -> Self {
         let (tx, rx) = flume::unbounded();
 
-        let _tx = tx.clone();
-        let _handler = tokio::spawn(async move {
-            let tx = _tx;
-            let mut reader = crossterm::event::EventStream::new();
-            let mut tick = tokio::time::interval(tick_rate);
+        tokio::spawn(supervise(tx.clone(), read_events(tx.clone(), tick_rate)));
 
-            loop {
-                let tick_delay = tick.tick();
-                let crossterm_event = reader.next().fuse();
+        Self { tx, rx }
+    }
 
-                tokio::select! {
-                    _ = tick_delay => {
-                        tx.send(Event::Tick).unwrap() // NOTE: Arguably I don't really need the
-                                                      // ticks
-                    },
-                    Some(Ok(event)) = crossterm_event => {
-                        match event {
-                            crossterm::event::Event::Key(key) => {
-                                if key.kind == crossterm::event::KeyEventKind::Press {
-                                    tx.send(Event::Key(key)).unwrap();
-                                }
-                            },
-                            crossterm::event::Event::Mouse(mouse) => {
-                                tx.send(Event::Mouse(mouse)).unwrap();
-                            },
-                            crossterm::event::Event::Resize(x, y) => {
-                                tx.send(Event::Resize(x, y)).unwrap();
-                            },
-                            crossterm::event::Event::FocusLost => {},
-                            crossterm::event::Event::FocusGained => {},
-                            crossterm::event::Event::Paste(_) => {},
-                        }
-                    }
-                }
-            }
-        });
+    /// Handler without a terminal reader, events only arrive through `tx`
+    #[cfg(test)]
+    pub(crate) fn detached() -> Self {
+        let (tx, rx) = flume::unbounded();
 
         Self { tx, rx }
     }
@@ -72,3 +48,79 @@ impl EventHandler {
         self.tx.clone()
     }
 }
+
+/// Run the reader, turning a panic into `Event::Fatal`. The panic hook has already restored
+/// the terminal by then, so restarting the reader would leave a UI nobody can see.
+async fn supervise(tx: flume::Sender<Event>, reader: impl Future<Output = ()> + Send + 'static) {
+    if let Err(err) = tokio::spawn(reader).await {
+        if err.is_panic() {
+            let message = panic_message(err.into_panic());
+            tx.send(Event::Fatal(format!("Event reader panicked: {message}")))
+                .ok();
+        }
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown cause".to_string(),
+        },
+    }
+}
+
+async fn read_events(tx: flume::Sender<Event>, tick_rate: Duration) {
+    let mut reader = crossterm::event::EventStream::new();
+    let mut tick = tokio::time::interval(tick_rate);
+
+    loop {
+        let tick_delay = tick.tick();
+        let crossterm_event = reader.next().fuse();
+
+        tokio::select! {
+            _ = tick_delay => {
+                tx.send(Event::Tick).unwrap() // NOTE: Arguably I don't really need the
+                                              // ticks
+            },
+            Some(Ok(event)) = crossterm_event => {
+                match event {
+                    crossterm::event::Event::Key(key) => {
+                        if key.kind == crossterm::event::KeyEventKind::Press {
+                            tx.send(Event::Key(key)).unwrap();
+                        }
+                    },
+                    crossterm::event::Event::Mouse(mouse) => {
+                        tx.send(Event::Mouse(mouse)).unwrap();
+                    },
+                    crossterm::event::Event::Resize(x, y) => {
+                        tx.send(Event::Resize(x, y)).unwrap();
+                    },
+                    crossterm::event::Event::FocusLost => {},
+                    crossterm::event::Event::FocusGained => {},
+                    crossterm::event::Event::Paste(text) => {
+                        tx.send(Event::Paste(text)).unwrap();
+                    },
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reader_panic_surfaces_as_fatal_event() {
+        let (tx, rx) = flume::unbounded();
+
+        supervise(tx, async { panic!("terminal went away") }).await;
+
+        match rx.recv_async().await {
+            Ok(Event::Fatal(message)) => assert!(message.contains("terminal went away")),
+            _ => panic!("expected a fatal event"),
+        }
+    }
+}
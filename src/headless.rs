@@ -0,0 +1,83 @@
+use std::io::{self, Write};
+
+use crossterm::style::Stylize;
+
+use crate::config::{Config, Node};
+
+/// Plain text output for when there is no terminal to draw the TUI on, `color` adds ANSI
+/// styling for headings and disabled nodes
+pub fn run(config: &Config, mut out: impl Write, color: bool) -> io::Result<()> {
+    let servers = config.servers();
+    if let Some(name) = config.cluster_name() {
+        writeln!(out, "Cluster: {name}")?;
+    }
+    if let Some(vip) = &servers.vip {
+        writeln!(out, "VIP: {vip}")?;
+    }
+    for (name, vip) in servers.vips.iter() {
+        writeln!(out, "VIP ({name}): {vip}")?;
+    }
+    if let Some(node) = servers.primary_control() {
+        writeln!(out, "Bootstrap node: {}", node.address)?;
+    }
+
+    write_nodes(&mut out, "Control nodes", &servers.control, color)?;
+    write_nodes(&mut out, "Worker nodes", &servers.worker, color)?;
+
+    out.flush()
+}
+
+fn write_nodes(out: &mut impl Write, title: &str, nodes: &[Node], color: bool) -> io::Result<()> {
+    if color {
+        writeln!(out, "{}", format!("{title}:").bold())?;
+    } else {
+        writeln!(out, "{title}:")?;
+    }
+    if nodes.is_empty() {
+        writeln!(out, "  (none)")?;
+    }
+
+    for node in nodes {
+        if node.enabled {
+            writeln!(out, "  {}", node.address)?;
+        } else if color {
+            writeln!(out, "  {}", format!("{} (disabled)", node.address).dim())?;
+        } else {
+            writeln!(out, "  {} (disabled)", node.address)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        [servers]
+        control = ["10.0.0.1", { address = "10.0.0.2", enabled = false }]
+    "#;
+
+    fn output(color: bool) -> String {
+        let config = toml::from_str::<Config>(CONFIG).unwrap();
+        let mut out = vec![];
+        run(&config, &mut out, color).unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn color_can_be_left_out() {
+        assert!(output(true).contains('\x1b'));
+
+        let plain = output(false);
+        assert!(!plain.contains('\x1b'));
+        assert_eq!(
+            plain,
+            "Bootstrap node: 10.0.0.1\n\
+             Control nodes:\n  10.0.0.1\n  10.0.0.2 (disabled)\n\
+             Worker nodes:\n  (none)\n"
+        );
+    }
+}
@@ -1,4 +1,9 @@
+pub mod action;
 pub mod app;
 pub mod config;
+pub mod doctor;
 pub mod event;
+pub mod headless;
 pub mod log;
+pub mod metrics;
+pub mod rke2;
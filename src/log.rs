@@ -1,4 +1,14 @@
-use std::{collections::HashMap, fmt::Display, fs::OpenOptions, sync::Arc};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    collections::VecDeque,
+    fmt::Display,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
 
 use chrono::{DateTime, Local};
 use ratatui::{
@@ -6,10 +16,13 @@ use ratatui::{
     text::{Line, Span},
     widgets::ListItem,
 };
+use serde::Deserialize;
+use serde_json::Value;
 use tracing::{
     field::{Field, Visit},
     info, Level,
 };
+use tracing_appender::non_blocking::{NonBlocking, NonBlockingBuilder, WorkerGuard};
 
 use tracing_subscriber::{
     fmt,
@@ -19,7 +32,55 @@ use tracing_subscriber::{
     EnvFilter, Layer,
 };
 
-use crate::event::{Event, EventHandler};
+use crate::{
+    event::{Event, EventHandler},
+    metrics::{Metrics, MetricsLayer},
+};
+
+/// Values that must never show up in the log pane or log file
+static SECRETS: RwLock<Vec<Arc<str>>> = RwLock::new(Vec::new());
+
+/// Register a secret (cluster token, password...) to be masked in all log output
+pub fn add_secret(secret: impl Into<Arc<str>>) {
+    let secret = secret.into();
+    if secret.is_empty() {
+        return;
+    }
+
+    let mut secrets = SECRETS.write().unwrap_or_else(|err| err.into_inner());
+    if !secrets.contains(&secret) {
+        secrets.push(secret);
+    }
+}
+
+fn redact(text: &str) -> Cow<'_, str> {
+    let secrets = SECRETS.read().unwrap_or_else(|err| err.into_inner());
+
+    let mut text = Cow::Borrowed(text);
+    for secret in secrets.iter() {
+        if text.contains(secret.as_ref()) {
+            text = Cow::Owned(text.replace(secret.as_ref(), "****"));
+        }
+    }
+
+    text
+}
+
+/// Masks secrets in whatever the file layer writes
+struct Redacted<W>(W);
+
+impl<W: Write> Write for Redacted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.0.write_all(redact(&text).as_bytes())?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
 
 struct TuiLayer<T> {
     tx: flume::Sender<T>,
@@ -49,10 +110,36 @@ pub struct LogEvent {
     name: Arc<str>,
     fields: Fields,
     timestamp: DateTime<Local>,
+    /// Emit order, unaffected by wall clock changes
+    instant: Instant,
     span: Option<LogSpan>,
 }
 
 impl LogEvent {
+    /// Event that didn't go through `tracing`, e.g. raised before the logger was set up or
+    /// that must show whatever `RUST_LOG` filters
+    pub fn new(level: Level, target: &str, message: &str) -> Self {
+        let fields = HashMap::from([("message".into(), redact(message).as_ref().into())]);
+
+        Self {
+            level,
+            target: target.into(),
+            name: "event".into(),
+            fields: Fields(fields),
+            timestamp: Local::now(),
+            instant: Instant::now(),
+            span: None,
+        }
+    }
+
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    pub fn instant(&self) -> Instant {
+        self.instant
+    }
+
     fn with_span(mut self, span: LogSpan) -> Self {
         self.span = Some(span);
 
@@ -65,8 +152,9 @@ struct Fields(HashMap<Arc<str>, Arc<str>>);
 
 impl Visit for Fields {
     fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        let value = format!("{:?}", value);
         self.0
-            .insert(field.name().into(), format!("{:?}", value).into());
+            .insert(field.name().into(), redact(&value).as_ref().into());
     }
 }
 
@@ -95,6 +183,7 @@ impl<'a> From<&'a tracing::Event<'a>> for LogEvent {
         value.record(&mut fields);
 
         let timestamp = Local::now();
+        let instant = Instant::now();
 
         Self {
             level,
@@ -102,11 +191,131 @@ impl<'a> From<&'a tracing::Event<'a>> for LogEvent {
             name,
             fields,
             timestamp,
+            instant,
             span: None,
         }
     }
 }
 
+/// A line of the JSON log file, as written by the `fmt` layer in `init_logger`
+#[derive(Deserialize)]
+struct JsonLine {
+    timestamp: Box<str>,
+    level: Box<str>,
+    target: Arc<str>,
+    #[serde(default)]
+    fields: HashMap<Box<str>, Value>,
+    #[serde(default)]
+    spans: Vec<JsonSpan>,
+}
+
+#[derive(Deserialize)]
+struct JsonSpan {
+    name: Box<str>,
+}
+
+impl TryFrom<JsonLine> for LogEvent {
+    type Error = ();
+
+    fn try_from(line: JsonLine) -> Result<Self, Self::Error> {
+        let level = line.level.parse().map_err(|_| ())?;
+        let timestamp = DateTime::parse_from_rfc3339(&line.timestamp)
+            .map_err(|_| ())?
+            .with_timezone(&Local);
+
+        let fields = line
+            .fields
+            .into_iter()
+            .map(|(name, value)| {
+                // Match `record_debug`, which doesn't quote the message
+                let value = match value {
+                    Value::String(message) if name.as_ref() == "message" => message,
+                    value => value.to_string(),
+                };
+                (name.as_ref().into(), redact(&value).as_ref().into())
+            })
+            .collect();
+
+        // The file lists spans from the root down, `LogSpan` goes from the leaf up
+        let span = (!line.spans.is_empty()).then(|| LogSpan {
+            scope: line
+                .spans
+                .iter()
+                .rev()
+                .map(|span| span.name.as_ref())
+                .collect::<Vec<_>>()
+                .join(":")
+                .into(),
+        });
+
+        // No monotonic clock survives a restart, so place the event by its age instead
+        let age = (Local::now() - timestamp).to_std().unwrap_or_default();
+        let instant = Instant::now().checked_sub(age).unwrap_or_else(Instant::now);
+
+        Ok(Self {
+            level,
+            target: line.target,
+            name: "event".into(),
+            fields: Fields(fields),
+            timestamp,
+            instant,
+            span,
+        })
+    }
+}
+
+/// Limits on which historical events get preloaded
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PreloadFilter {
+    /// Only events newer than this
+    pub since: Option<Duration>,
+    /// Only events at least this severe
+    pub min_level: Option<Level>,
+}
+
+impl PreloadFilter {
+    fn matches(&self, event: &LogEvent, now: DateTime<Local>) -> bool {
+        let recent = self.since.is_none_or(|since| {
+            chrono::Duration::from_std(since).is_ok_and(|since| event.timestamp >= now - since)
+        });
+        // Levels compare by verbosity, so more severe is smaller
+        let severe = self.min_level.is_none_or(|level| event.level <= level);
+
+        recent && severe
+    }
+}
+
+/// Read back the last `lines` events of a previous run's log file that pass `filter`,
+/// skipping malformed lines
+pub fn preload(path: &Path, lines: usize, filter: &PreloadFilter) -> io::Result<Vec<LogEvent>> {
+    if lines == 0 {
+        return Ok(vec![]);
+    }
+
+    let reader = BufReader::new(File::open(path)?);
+    let now = Local::now();
+
+    let mut tail = VecDeque::with_capacity(lines);
+    for line in reader.lines() {
+        let Ok(line) = serde_json::from_str::<JsonLine>(&line?) else {
+            continue;
+        };
+        let Ok(event) = LogEvent::try_from(line) else {
+            continue;
+        };
+        if !filter.matches(&event, now) {
+            continue;
+        }
+
+        if tail.len() == lines {
+            tail.pop_front();
+        }
+        tail.push_back(event);
+    }
+
+    Ok(tail.into())
+}
+
 impl Display for LogEvent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let _name = self.name.as_ref();
@@ -119,7 +328,11 @@ impl Display for LogEvent {
         let level = self.level;
         let fields = &self.fields.0;
 
-        write!(f, "{timestamp} {level:5} {scope:30.30} {fields:?}",)
+        write!(
+            f,
+            "{timestamp} {level:5} {scope:width$.width$} {fields:?}",
+            width = DEFAULT_SCOPE_WIDTH
+        )
     }
 }
 
@@ -136,50 +349,297 @@ where
     }
 }
 
-impl From<&'_ LogEvent> for ListItem<'_> {
-    fn from(event: &'_ LogEvent) -> Self {
-        let style = match event.level {
-            Level::INFO => Style::default().fg(Color::Green),
-            Level::DEBUG => Style::default().fg(Color::Blue),
-            Level::TRACE => Style::default().fg(Color::White),
-            Level::WARN => Style::default().fg(Color::Yellow),
-            Level::ERROR => Style::default().fg(Color::Red),
-        };
+/// Display options for the log pane, the log file is unaffected
+#[derive(Clone, Copy, Debug)]
+pub struct LogFormat {
+    pub timestamps: bool,
+    /// Columns the target and span scope are padded or truncated to
+    pub scope_width: usize,
+}
+
+impl Default for LogFormat {
+    fn default() -> Self {
+        Self {
+            timestamps: true,
+            scope_width: DEFAULT_SCOPE_WIDTH,
+        }
+    }
+}
 
-        let timestamp = event.timestamp.format("[%Y-%m-%d][%H:%M:%S%.6f]");
-        let level = event.level;
-        let scope = if let Some(span) = &event.span {
-            format!("{}:{}", event.target, span.scope)
+const DEFAULT_SCOPE_WIDTH: usize = 30;
+const SCOPE_WIDTH_RANGE: (usize, usize) = (12, 60);
+
+/// Scope column width for a log pane `pane_width` columns wide, a quarter of the pane within
+/// sensible bounds
+pub fn scope_width(pane_width: u16) -> usize {
+    (pane_width as usize / 4).clamp(SCOPE_WIDTH_RANGE.0, SCOPE_WIDTH_RANGE.1)
+}
+
+/// Colour of a level in the log pane
+pub fn level_style(level: Level) -> Style {
+    match level {
+        Level::INFO => Style::default().fg(Color::Green),
+        Level::DEBUG => Style::default().fg(Color::Blue),
+        Level::TRACE => Style::default().fg(Color::White),
+        Level::WARN => Style::default().fg(Color::Yellow),
+        Level::ERROR => Style::default().fg(Color::Red),
+    }
+}
+
+/// Every level in its own colour, most severe first
+pub fn legend() -> Line<'static> {
+    let levels = [
+        Level::ERROR,
+        Level::WARN,
+        Level::INFO,
+        Level::DEBUG,
+        Level::TRACE,
+    ];
+
+    let mut spans = vec![];
+    for level in levels {
+        spans.push(Span::styled(format!(" {level} "), level_style(level)));
+    }
+
+    Line::from(spans)
+}
+
+impl LogEvent {
+    pub fn to_list_item(&self, format: &LogFormat) -> ListItem<'static> {
+        let style = level_style(self.level);
+
+        let timestamp = self.timestamp.format("[%Y-%m-%d][%H:%M:%S%.6f]");
+        let level = self.level;
+        let scope = if let Some(span) = &self.span {
+            format!("{}:{}", self.target, span.scope)
         } else {
-            event.target.to_string()
+            self.target.to_string()
         };
-        let fields = &event.fields.0;
+        let fields = &self.fields.0;
 
-        let content = vec![Line::from(vec![
-            Span::raw(timestamp.to_string()),
-            Span::styled(format!(" {level:<5} "), style),
-            Span::raw(scope),
+        let mut spans = vec![];
+        if format.timestamps {
+            spans.push(Span::raw(format!("{timestamp} ")));
+        }
+        spans.extend([
+            Span::styled(format!("{level:<5} "), style),
+            Span::raw(format!("{scope:width$.width$}", width = format.scope_width)),
             Span::raw(format!(" {fields:?}")),
-        ])];
+        ]);
 
-        Self::new(content)
+        ListItem::new(Line::from(spans))
     }
 }
 
-pub fn init_logger(event_handler: &EventHandler) {
+pub const LOG_FILE: &str = "ez_rke.log";
+
+/// A log storm shouldn't stall whichever thread is emitting on disk I/O, so writes are queued
+/// for a background thread. The queue blocks rather than drops lines once full, losing the
+/// middle of a deploy log is worse than a brief stall.
+fn file_writer<W>(writer: W) -> (NonBlocking, WorkerGuard)
+where
+    W: Write + Send + 'static,
+{
+    NonBlockingBuilder::default()
+        .lossy(false)
+        .finish(Redacted(writer))
+}
+
+/// Logs written to the file are flushed from a background thread, keep the returned guard
+/// alive until exit or the tail of the log is lost
+pub fn init_logger(event_handler: &EventHandler, metrics: Option<Arc<Metrics>>) -> WorkerGuard {
     let logging_file = OpenOptions::new()
         .append(true)
         .create(true)
-        .open("ez_rke.log")
+        .open(LOG_FILE)
         .unwrap();
 
+    let (file_writer, guard) = file_writer(logging_file);
+
     let tui_layer = TuiLayer::new(event_handler.tx());
 
     tracing_subscriber::registry()
-        .with(fmt::layer().json().with_writer(logging_file))
+        .with(fmt::layer().json().with_writer(file_writer))
         .with(tui_layer)
+        .with(metrics.map(MetricsLayer))
         .with(EnvFilter::from_default_env())
         .init();
 
     info!("Initialized ez_rke loggers...");
+
+    guard
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Writer whose output stays readable after it's moved to the background thread
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn secrets_are_masked_in_the_pane() {
+        add_secret("s3cr3t-t0ken");
+        let event = LogEvent::new(Level::INFO, "ez_rke", "joining with s3cr3t-t0ken");
+
+        let item = format!("{:?}", event.to_list_item(&LogFormat::default()));
+
+        assert!(!item.contains("s3cr3t-t0ken"));
+        assert!(item.contains("joining with ****"));
+    }
+
+    #[test]
+    fn file_writer_masks_secrets() {
+        add_secret("hunter2-file");
+        let output = Shared::default();
+        let (mut writer, guard) = file_writer(output.clone());
+
+        writeln!(writer, "password=hunter2-file").unwrap();
+        drop(writer);
+        drop(guard);
+
+        assert_eq!(*output.0.lock().unwrap(), b"password=****\n");
+    }
+
+    #[test]
+    fn file_writer_flushes_every_line_of_a_burst() {
+        let output = Shared::default();
+        let (mut writer, guard) = file_writer(output.clone());
+
+        for line in 0..100_000 {
+            writeln!(writer, "line {line}").unwrap();
+        }
+        drop(writer);
+        drop(guard);
+
+        let output = output.0.lock().unwrap();
+        let lines = String::from_utf8_lossy(&output);
+        assert_eq!(lines.lines().count(), 100_000);
+        assert_eq!(lines.lines().last(), Some("line 99999"));
+    }
+
+    /// JSON log line as the file layer writes it, `age` before now
+    fn json_line(age: chrono::Duration, level: &str, message: &str) -> String {
+        let timestamp = (Local::now() - age).to_rfc3339();
+        format!(
+            r#"{{"timestamp":"{timestamp}","level":"{level}","fields":{{"message":"{message}"}},"target":"ez_rke"}}"#
+        )
+    }
+
+    fn log_file(name: &str, lines: &[String]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("ez-rke-{}-{name}", std::process::id()));
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        path
+    }
+
+    #[test]
+    fn preload_rebuilds_events_from_the_log_file() {
+        let minute = chrono::Duration::minutes(1);
+        let lines = [
+            json_line(minute * 3, "INFO", "started"),
+            "not json".to_string(),
+            json_line(minute * 2, "WARN", "slow node"),
+            json_line(minute, "ERROR", "join failed"),
+        ];
+        let path = log_file("preload.log", &lines);
+
+        let events = preload(&path, 2, &PreloadFilter::default()).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let levels = events.iter().map(LogEvent::level).collect::<Vec<_>>();
+        assert_eq!(levels, [Level::WARN, Level::ERROR]);
+        assert_eq!(&*events[1].fields.0["message"], "join failed");
+        let age = Local::now() - events[1].timestamp;
+        assert!(age >= minute && age < minute * 2, "{age}");
+        assert!(events[0].instant() < events[1].instant());
+    }
+
+    #[test]
+    fn timestamps_can_be_left_out() {
+        let event = LogEvent::new(Level::INFO, "ez_rke", "joined");
+        let date = event.timestamp.format("[%Y-%m-%d]").to_string();
+        let render = |timestamps| {
+            let format = LogFormat {
+                timestamps,
+                ..LogFormat::default()
+            };
+            format!("{:?}", event.to_list_item(&format))
+        };
+
+        assert!(render(true).contains(&date));
+        assert!(!render(false).contains(&date));
+        assert!(render(false).contains("joined"));
+    }
+
+    #[test]
+    fn preload_filters_by_age_and_level() {
+        let minute = chrono::Duration::minutes(1);
+        let lines = [
+            json_line(minute * 90, "ERROR", "old error"),
+            json_line(minute * 10, "DEBUG", "recent debug"),
+            json_line(minute * 5, "WARN", "recent warning"),
+            json_line(minute, "ERROR", "recent error"),
+        ];
+        let path = log_file("filtered.log", &lines);
+        let filter = PreloadFilter {
+            since: Some(Duration::from_secs(60 * 60)),
+            min_level: Some(Level::WARN),
+        };
+
+        let events = preload(&path, 10, &filter).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let messages = events
+            .iter()
+            .map(|event| event.fields.0["message"].as_ref())
+            .collect::<Vec<_>>();
+        assert_eq!(messages, ["recent warning", "recent error"]);
+    }
+
+    #[test]
+    fn legend_lists_every_level_in_its_colour() {
+        let legend = legend();
+
+        let levels = legend
+            .spans
+            .iter()
+            .map(|span| (span.content.trim().to_string(), span.style.fg))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            levels,
+            [
+                ("ERROR".to_string(), Some(Color::Red)),
+                ("WARN".to_string(), Some(Color::Yellow)),
+                ("INFO".to_string(), Some(Color::Green)),
+                ("DEBUG".to_string(), Some(Color::Blue)),
+                ("TRACE".to_string(), Some(Color::White)),
+            ]
+        );
+    }
+
+    #[test]
+    fn scope_width_follows_the_pane_within_bounds() {
+        assert_eq!(scope_width(0), 12);
+        assert_eq!(scope_width(40), 12);
+        assert_eq!(scope_width(80), 20);
+        assert_eq!(scope_width(120), 30);
+        assert_eq!(scope_width(240), 60);
+        assert_eq!(scope_width(u16::MAX), 60);
+    }
 }
@@ -1,29 +1,312 @@
-use std::{fs, io, path::PathBuf, time::Duration};
+use std::{
+    io::{self, IsTerminal, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+    time::Duration,
+};
 
-use clap::Parser;
-use ez_rke::{app::App, event::EventHandler, log::init_logger};
+use clap::{Parser, Subcommand, ValueEnum};
+use ez_rke::{
+    app::App,
+    config::{
+        Config, ConfigError, ConfigFormat, ValidationReport, DEFAULT_RKE2_CHANNEL, SCHEMA_VERSION,
+    },
+    doctor,
+    event::EventHandler,
+    headless,
+    log::{self, init_logger, PreloadFilter, LOG_FILE},
+    metrics::{self, Metrics},
+    rke2,
+};
+use tracing::Level;
 
 /// Simple automation tool to configure a clustered RKE2 service
 #[derive(Parser, Debug)]
 #[command(version, about)]
 struct Args {
-    /// Name of the configuration file
+    /// Name of the configuration file, `-` reads it from stdin
     #[arg(short, long, default_value = "./config.toml")]
     config: PathBuf,
+
+    /// Format of the configuration file, detected from its extension by default
+    #[arg(long, value_enum)]
+    format: Option<ConfigFileFormat>,
+
+    /// Print plain text instead of starting the TUI
+    #[arg(long)]
+    headless: bool,
+
+    /// When to use colours, `auto` disables them when stdout isn't a terminal or `NO_COLOR`
+    /// is set
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Draw borders with plain ASCII, for terminals without box-drawing characters
+    #[arg(long)]
+    ascii: bool,
+
+    /// Use the servers from this profile instead of the top-level `[servers]`
+    #[arg(short, long)]
+    profile: Option<String>,
+
+    /// Only deploy to these hosts, e.g. `--nodes 192.168.1.21,192.168.1.22`
+    #[arg(long, value_delimiter = ',')]
+    nodes: Vec<String>,
+
+    /// Serve Prometheus metrics on this address, e.g. `127.0.0.1:9100`
+    #[arg(long, value_name = "ADDR")]
+    metrics: Option<SocketAddr>,
+
+    /// Load the last LINES entries of the previous run's log into the log pane
+    #[arg(long, value_name = "LINES", default_value_t = 0)]
+    preload_logs: usize,
+
+    /// Only preload logs newer than this, e.g. `30m` or `2h`
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    log_since: Option<Duration>,
+
+    /// Only preload logs at or above this level
+    #[arg(long, value_name = "LEVEL")]
+    log_min_level: Option<Level>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check the configuration file and exit
+    Validate {
+        /// Format of the report printed to stdout
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Check that this machine has everything needed to run deploys
+    Doctor,
+    /// Print the install command and RKE2 config each deployable node would get
+    Render,
+    /// Print the version
+    Version {
+        /// Also print the RKE2 channel deployed by default and the config schema version
+        #[arg(short, long)]
+        verbose: bool,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ConfigFileFormat {
+    Toml,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            // https://no-color.org: any non-empty value disables colour
+            ColorChoice::Auto => {
+                io::stdout().is_terminal()
+                    && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            }
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum OutputFormat {
+    Text,
+    Json,
 }
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 10)]
-async fn main() -> io::Result<()> {
+async fn main() -> io::Result<ExitCode> {
     let args = Args::parse();
 
-    let event_handler = EventHandler::new(Duration::from_millis(250));
-    init_logger(&event_handler);
+    if let Some(Command::Validate { output }) = args.command {
+        let report = ValidationReport::from(&parse_config(&args).await);
+        match output {
+            OutputFormat::Text => print!("{report}"),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        }
+
+        return Ok(if report.valid {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        });
+    }
+
+    if let Some(Command::Version { verbose }) = args.command {
+        print!("{}", version(verbose));
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if let Some(Command::Doctor) = args.command {
+        let checks = [
+            doctor::config(&load_config(&args).await),
+            doctor::program("ssh", true),
+            doctor::program("kubectl", false),
+            doctor::log_file(Path::new(LOG_FILE)),
+        ];
+        let healthy = doctor::report(&checks, io::stdout().lock())?;
+
+        return Ok(if healthy {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        });
+    }
+
+    let config = match load_config(&args).await {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{err}");
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+
+    if let Some(Command::Render) = args.command {
+        rke2::render(&config, io::stdout().lock())?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if use_headless(
+        args.headless,
+        io::stdout().is_terminal(),
+        io::stderr().lock(),
+    )? {
+        for warning in config.warnings() {
+            eprintln!("warning: {warning}");
+        }
+        headless::run(&config, io::stdout().lock(), args.color.enabled())?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // A missing log file just means there is no previous run to continue from
+    let filter = PreloadFilter {
+        since: args.log_since,
+        min_level: args.log_min_level,
+    };
+    let logs = log::preload(Path::new(LOG_FILE), args.preload_logs, &filter).unwrap_or_default();
+
+    let event_handler = EventHandler::new(config.ui.tick_rate);
+    let metrics = args.metrics.map(|_| Arc::new(Metrics::default()));
+    // Dropping the guard flushes the file writer, so it has to outlive the app
+    let _log_guard = init_logger(&event_handler, metrics.clone());
+    if let (Some(addr), Some(metrics)) = (args.metrics, &metrics) {
+        metrics::serve(addr, metrics.clone())?;
+    }
+
+    let warnings = config.warnings();
+    let app = App::new(event_handler, config)
+        .map_err(|err| io::Error::new(err.kind(), format!("Unable to set up the terminal: {err}")))?
+        .with_logs(logs)
+        .with_warnings(warnings)
+        .with_metrics(metrics)
+        .with_color(args.color.enabled())
+        .with_ascii_borders(args.ascii);
+
+    app.run().await?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Headless when asked for, or when there is no terminal to draw the TUI on. Falling back
+/// says so on `notice`, plain output the user didn't ask for would be confusing otherwise.
+fn use_headless(headless: bool, is_terminal: bool, mut notice: impl Write) -> io::Result<bool> {
+    if !headless && !is_terminal {
+        writeln!(
+            notice,
+            "stdout is not a terminal, falling back to headless mode"
+        )?;
+    }
+
+    Ok(headless || !is_terminal)
+}
+
+fn version(verbose: bool) -> String {
+    let mut version = format!("ez-rke {}\n", env!("CARGO_PKG_VERSION"));
+    if verbose {
+        version.push_str(&format!("RKE2 default channel: {DEFAULT_RKE2_CHANNEL}\n"));
+        version.push_str(&format!("Config schema version: {SCHEMA_VERSION}\n"));
+    }
+
+    version
+}
+
+async fn load_config(args: &Args) -> Result<Config, ConfigError> {
+    let config = Config::load(&args.config, config_format(args)).await?;
+
+    select(config, args)
+}
+
+/// The config without validation, for reports that list every problem
+async fn parse_config(args: &Args) -> Result<Config, ConfigError> {
+    let config = Config::parse(&args.config, config_format(args)).await?;
+
+    select(config, args)
+}
+
+fn config_format(args: &Args) -> Option<ConfigFormat> {
+    args.format.map(|format| match format {
+        ConfigFileFormat::Toml => ConfigFormat::Toml,
+        ConfigFileFormat::Json => ConfigFormat::Json,
+    })
+}
+
+/// Apply `--profile` and `--nodes`
+fn select(mut config: Config, args: &Args) -> Result<Config, ConfigError> {
+    if let Some(profile) = &args.profile {
+        config.select_profile(profile)?;
+    }
+    if !args.nodes.is_empty() {
+        config.servers_mut().scope_to(&args.nodes)?;
+    }
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headless_without_a_terminal() {
+        let decide = |headless, is_terminal| {
+            let mut notice = vec![];
+            let headless = use_headless(headless, is_terminal, &mut notice).unwrap();
+            (headless, String::from_utf8(notice).unwrap())
+        };
+
+        assert_eq!(
+            decide(false, false),
+            (
+                true,
+                "stdout is not a terminal, falling back to headless mode\n".to_string()
+            )
+        );
+        // Asked for, so nothing to explain
+        assert_eq!(decide(true, false), (true, String::new()));
+        assert_eq!(decide(true, true), (true, String::new()));
+        assert_eq!(decide(false, true), (false, String::new()));
+    }
 
-    let app = App::new(
-        event_handler,
-        toml::from_str(&fs::read_to_string(args.config).expect("Unable to read config file"))
-            .expect("Unable to parse config file"),
-    );
+    #[test]
+    fn verbose_version_names_the_rke2_default() {
+        assert!(!version(false).contains(DEFAULT_RKE2_CHANNEL));
 
-    app.run().await
+        let verbose = version(true);
+        assert!(verbose.starts_with(&version(false)));
+        assert!(verbose.contains(&format!("RKE2 default channel: {DEFAULT_RKE2_CHANNEL}")));
+    }
 }
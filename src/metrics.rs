@@ -0,0 +1,167 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use tracing::{info, Level};
+use tracing_subscriber::Layer;
+
+use crate::config::Servers;
+
+const LEVELS: [Level; 5] = [
+    Level::ERROR,
+    Level::WARN,
+    Level::INFO,
+    Level::DEBUG,
+    Level::TRACE,
+];
+
+/// Counters exposed on the Prometheus endpoint
+#[derive(Default)]
+pub struct Metrics {
+    nodes_total: AtomicUsize,
+    nodes_enabled: AtomicUsize,
+    log_events: [AtomicUsize; 5],
+}
+
+impl Metrics {
+    pub fn set_nodes(&self, servers: &Servers) {
+        let total = servers.control.len() + servers.worker.len();
+        self.nodes_total.store(total, Ordering::Relaxed);
+        self.nodes_enabled
+            .store(servers.deployable().count(), Ordering::Relaxed);
+    }
+
+    fn record(&self, level: Level) {
+        if let Some(index) = LEVELS.iter().position(|l| *l == level) {
+            self.log_events[index].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+
+        text.push_str("# HELP ez_rke_nodes Configured nodes in the active cluster\n");
+        text.push_str("# TYPE ez_rke_nodes gauge\n");
+        let total = self.nodes_total.load(Ordering::Relaxed);
+        let enabled = self.nodes_enabled.load(Ordering::Relaxed);
+        writeln!(text, "ez_rke_nodes{{state=\"total\"}} {total}").ok();
+        writeln!(text, "ez_rke_nodes{{state=\"enabled\"}} {enabled}").ok();
+
+        text.push_str("# HELP ez_rke_log_events_total Log events by level\n");
+        text.push_str("# TYPE ez_rke_log_events_total counter\n");
+        for (level, count) in LEVELS.iter().zip(&self.log_events) {
+            let level = level.as_str().to_lowercase();
+            let count = count.load(Ordering::Relaxed);
+            writeln!(text, "ez_rke_log_events_total{{level=\"{level}\"}} {count}").ok();
+        }
+
+        text
+    }
+}
+
+/// Counts every log event by level
+pub struct MetricsLayer(pub Arc<Metrics>);
+
+impl<S> Layer<S> for MetricsLayer
+where
+    S: tracing::Subscriber,
+{
+    fn on_event(&self, event: &tracing::Event<'_>, _: tracing_subscriber::layer::Context<'_, S>) {
+        self.0.record(*event.metadata().level());
+    }
+}
+
+/// How long a scrape may stall before the connection is dropped, so a client that never
+/// sends or reads can't hold up the single serving thread
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serve the metrics over plain HTTP on a background thread, every request gets the full
+/// text
+pub fn serve(addr: SocketAddr, metrics: Arc<Metrics>) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    info!(%addr, "Serving metrics");
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            if stream.set_read_timeout(Some(CLIENT_TIMEOUT)).is_err()
+                || stream.set_write_timeout(Some(CLIENT_TIMEOUT)).is_err()
+            {
+                continue;
+            }
+
+            // The request itself doesn't matter, just drain what was sent
+            let mut buf = [0; 1024];
+            if stream.read(&mut buf).is_err() {
+                continue;
+            }
+
+            let body = metrics.render();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).ok();
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_reports_nodes_and_log_counts() {
+        let servers: Servers = toml::from_str(
+            r#"
+            control = ["10.0.0.1"]
+            worker = ["10.0.0.2", { address = "10.0.0.3", enabled = false }]
+            "#,
+        )
+        .unwrap();
+        let metrics = Metrics::default();
+        metrics.set_nodes(&servers);
+        metrics.record(Level::ERROR);
+        metrics.record(Level::INFO);
+        metrics.record(Level::INFO);
+
+        let text = metrics.render();
+
+        assert!(text.contains("# TYPE ez_rke_nodes gauge\n"));
+        assert!(text.contains("ez_rke_nodes{state=\"total\"} 3\n"));
+        assert!(text.contains("ez_rke_nodes{state=\"enabled\"} 2\n"));
+        assert!(text.contains("# TYPE ez_rke_log_events_total counter\n"));
+        assert!(text.contains("ez_rke_log_events_total{level=\"error\"} 1\n"));
+        assert!(text.contains("ez_rke_log_events_total{level=\"info\"} 2\n"));
+        assert!(text.contains("ez_rke_log_events_total{level=\"warn\"} 0\n"));
+    }
+
+    #[test]
+    fn serve_answers_with_the_metrics() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        serve(addr, Arc::new(Metrics::default())).unwrap();
+
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("ez_rke_nodes{state=\"total\"} 0"));
+    }
+}
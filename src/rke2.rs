@@ -0,0 +1,651 @@
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::config::{Config, Node, Servers, Vip};
+
+/// RKE2 `config.yaml` for `node`. Joining nodes point at the server URL, the bootstrap node
+/// starts the cluster and so has none.
+pub fn render_rke2_config(config: &Config, node: &Node) -> String {
+    let servers = config.servers();
+    let server = is_server(servers, node);
+    let mut yaml = String::new();
+
+    let bootstrap = servers
+        .primary_control()
+        .is_some_and(|primary| primary.address == node.address);
+    if !bootstrap {
+        if let Some(url) = servers.server_url() {
+            push_value(&mut yaml, "server", &url);
+        }
+    }
+
+    if let Some(data_dir) = &config.data_dir {
+        push_value(&mut yaml, "data-dir", &data_dir.to_string_lossy());
+    }
+    if let Some(ip) = &node.node_ip {
+        push_value(&mut yaml, "node-ip", ip);
+    }
+    if let Some(ip) = &node.node_external_ip {
+        push_value(&mut yaml, "node-external-ip", ip);
+    }
+
+    if server {
+        push_list(&mut yaml, "tls-san", servers.tls_san());
+        if let Some(snapshot) = &servers.etcd_snapshot {
+            push_value(
+                &mut yaml,
+                "etcd-snapshot-schedule-cron",
+                &snapshot.schedule_cron,
+            );
+            if let Some(retention) = snapshot.retention {
+                yaml.push_str(&format!("etcd-snapshot-retention: {retention}\n"));
+            }
+        }
+    }
+
+    let labels = servers
+        .labels(node)
+        .into_iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>();
+    push_list(&mut yaml, "node-label", labels.iter().map(String::as_str));
+    if server {
+        push_list(&mut yaml, "node-taint", servers.control_taints());
+    }
+
+    yaml
+}
+
+/// Where a node's own install script is uploaded before it's run
+pub const REMOTE_INSTALL_SCRIPT: &str = "/tmp/ez-rke-install.sh";
+
+/// Shell command that installs RKE2 on `node`, its own install script when it has one and
+/// otherwise the stock installer
+pub fn install_command(config: &Config, node: &Node) -> String {
+    let kind = if is_server(config.servers(), node) {
+        "server"
+    } else {
+        "agent"
+    };
+    let mut env = format!(
+        "INSTALL_RKE2_CHANNEL={} INSTALL_RKE2_TYPE={}",
+        shell_quote(&config.servers().rke2_channel()),
+        shell_quote(kind)
+    );
+    for (name, value) in mirror_env(config) {
+        env.push_str(&format!(" {name}={}", shell_quote(&value)));
+    }
+
+    match &node.install_script {
+        Some(_) => format!("{env} sh {}", shell_quote(REMOTE_INSTALL_SCRIPT)),
+        None => format!(
+            "curl -sfL {} | {env} sh -",
+            shell_quote(&config.install_script_url())
+        ),
+    }
+}
+
+/// Installer variables pointing the channel lookup and the release downloads at the mirror,
+/// which is laid out like update.rke2.io (`v1-release/channels`) and the GitHub releases
+/// (`releases/download/<version>`) under one base
+fn mirror_env(config: &Config) -> Vec<(&'static str, String)> {
+    let Some(base) = &config.install_url_base else {
+        return vec![];
+    };
+    let base = base.trim_end_matches('/');
+
+    vec![
+        (
+            "INSTALL_RKE2_CHANNEL_URL",
+            format!("{base}/v1-release/channels"),
+        ),
+        ("INSTALL_RKE2_GITHUB_URL", base.to_string()),
+    ]
+}
+
+/// RKE2's `data-dir` unless the config moves it
+const DEFAULT_DATA_DIR: &str = "/var/lib/rancher/rke2";
+
+/// Directory RKE2 applies manifests from on server nodes
+fn manifests_dir(config: &Config) -> PathBuf {
+    let data_dir = config.data_dir.as_deref();
+
+    data_dir
+        .unwrap_or(Path::new(DEFAULT_DATA_DIR))
+        .join("server/manifests")
+}
+
+/// Where each configured manifest is uploaded on the bootstrap node. RKE2 applies its
+/// manifests directory in file name order, so a numeric prefix keeps the configured order.
+pub fn manifest_targets(config: &Config) -> Vec<(&Path, PathBuf)> {
+    let dir = manifests_dir(config);
+
+    config
+        .manifests
+        .iter()
+        .enumerate()
+        .map(|(index, manifest)| {
+            let name = manifest.file_name().unwrap_or_default().to_string_lossy();
+            (manifest.as_path(), dir.join(format!("{index:02}-{name}")))
+        })
+        .collect()
+}
+
+/// Where the generated kube-vip manifest is written on the bootstrap node
+pub fn kube_vip_target(config: &Config) -> PathBuf {
+    manifests_dir(config).join("kube-vip.yaml")
+}
+
+/// kube-vip manifest announcing every VIP from the control nodes, `None` without an API VIP
+/// as extra VIPs alone don't make an HA cluster
+pub fn kube_vip_manifest(config: &Config) -> Option<String> {
+    let servers = config.servers();
+    servers.vip.as_ref()?;
+
+    let mut yaml = KUBE_VIP_RBAC.to_string();
+    for (name, vip) in servers.vips() {
+        yaml.push_str(&kube_vip_daemon_set(config, name, vip));
+    }
+
+    Some(yaml)
+}
+
+/// Account kube-vip runs as, allowed to take its leader election leases
+const KUBE_VIP_RBAC: &str = "\
+apiVersion: v1
+kind: ServiceAccount
+metadata:
+  name: kube-vip
+  namespace: kube-system
+---
+apiVersion: rbac.authorization.k8s.io/v1
+kind: ClusterRole
+metadata:
+  name: system:kube-vip-role
+rules:
+  - apiGroups: [\"\"]
+    resources: [\"services\", \"services/status\", \"nodes\", \"endpoints\"]
+    verbs: [\"list\", \"get\", \"watch\", \"update\"]
+  - apiGroups: [\"coordination.k8s.io\"]
+    resources: [\"leases\"]
+    verbs: [\"list\", \"get\", \"watch\", \"update\", \"create\"]
+---
+apiVersion: rbac.authorization.k8s.io/v1
+kind: ClusterRoleBinding
+metadata:
+  name: system:kube-vip-binding
+roleRef:
+  apiGroup: rbac.authorization.k8s.io
+  kind: ClusterRole
+  name: system:kube-vip-role
+subjects:
+  - kind: ServiceAccount
+    name: kube-vip
+    namespace: kube-system
+";
+
+/// DaemonSet on the control nodes announcing `vip` over ARP, the leader of its own lease
+/// holds the address
+fn kube_vip_daemon_set(config: &Config, name: &str, vip: &Vip) -> String {
+    let mut env = vec![
+        ("vip_arp", "true".to_string()),
+        ("port", vip.port.to_string()),
+        ("cp_enable", "true".to_string()),
+        ("cp_namespace", "kube-system".to_string()),
+        ("vip_leaderelection", "true".to_string()),
+        ("vip_leasename", format!("kube-vip-{name}")),
+        ("address", vip.address.to_string()),
+    ];
+    // kube-vip picks the interface of the default route otherwise
+    if let Some(interface) = &vip.interface {
+        env.push(("vip_interface", interface.to_string()));
+    }
+    if let Some(cidr) = vip.cidr {
+        env.push(("vip_cidr", cidr.to_string()));
+    }
+    let env = env
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "            - name: {name}\n              value: {}\n",
+                quote(value)
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        "---
+apiVersion: apps/v1
+kind: DaemonSet
+metadata:
+  name: kube-vip-{name}
+  namespace: kube-system
+spec:
+  selector:
+    matchLabels:
+      app.kubernetes.io/name: kube-vip-{name}
+  template:
+    metadata:
+      labels:
+        app.kubernetes.io/name: kube-vip-{name}
+    spec:
+      affinity:
+        nodeAffinity:
+          requiredDuringSchedulingIgnoredDuringExecution:
+            nodeSelectorTerms:
+              - matchExpressions:
+                  - key: node-role.kubernetes.io/control-plane
+                    operator: Exists
+      containers:
+        - name: kube-vip
+          image: {}
+          args: [\"manager\"]
+          env:
+{env}          securityContext:
+            capabilities:
+              add: [\"NET_ADMIN\", \"NET_RAW\"]
+      hostNetwork: true
+      serviceAccountName: kube-vip
+      tolerations:
+        - effect: NoSchedule
+          operator: Exists
+        - effect: NoExecute
+          operator: Exists
+",
+        quote(&format!(
+            "ghcr.io/kube-vip/kube-vip:{}",
+            config.kube_vip_version()
+        ))
+    )
+}
+
+/// Every deployable node's role and generated config, for the `render` subcommand
+pub fn render(config: &Config, mut out: impl Write) -> io::Result<()> {
+    let servers = config.servers();
+    for node in servers.deployable() {
+        let role = if is_server(servers, node) {
+            "server"
+        } else {
+            "agent"
+        };
+        writeln!(out, "# {} ({role})", node.address)?;
+        if let Some(script) = &node.install_script {
+            writeln!(
+                out,
+                "# upload {} to {REMOTE_INSTALL_SCRIPT}",
+                script.display()
+            )?;
+        }
+        writeln!(out, "# $ {}", install_command(config, node))?;
+        write!(out, "{}", render_rke2_config(config, node))?;
+        writeln!(out)?;
+    }
+
+    if let (Some(node), Some(manifest)) = (servers.primary_control(), kube_vip_manifest(config)) {
+        writeln!(
+            out,
+            "# {} (written on {})",
+            kube_vip_target(config).display(),
+            node.address
+        )?;
+        writeln!(out, "{manifest}")?;
+    }
+
+    let targets = manifest_targets(config);
+    if let (Some(node), false) = (servers.primary_control(), targets.is_empty()) {
+        writeln!(out, "# manifests (uploaded to {})", node.address)?;
+        for (manifest, target) in targets {
+            writeln!(out, "{} -> {}", manifest.display(), target.display())?;
+        }
+    }
+
+    out.flush()
+}
+
+fn is_server(servers: &Servers, node: &Node) -> bool {
+    servers
+        .control
+        .iter()
+        .any(|control| control.address == node.address)
+}
+
+/// Single quotes keep the shell from expanding anything, only a quote itself needs care
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// JSON strings are valid YAML scalars and escape everything YAML would need escaping
+fn quote(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn push_value(yaml: &mut String, key: &str, value: &str) {
+    yaml.push_str(&format!("{key}: {}\n", quote(value)));
+}
+
+/// Empty lists are left out rather than written as `[]`, RKE2's defaults apply instead
+fn push_list<'a>(yaml: &mut String, key: &str, values: impl IntoIterator<Item = &'a str>) {
+    let mut values = values.into_iter().peekable();
+    if values.peek().is_none() {
+        return;
+    }
+
+    yaml.push_str(&format!("{key}:\n"));
+    for value in values {
+        yaml.push_str(&format!("  - {}\n", quote(value)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> Config {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn node<'a>(config: &'a Config, address: &str) -> &'a Node {
+        let servers = config.servers();
+        servers
+            .control
+            .iter()
+            .chain(servers.worker.iter())
+            .find(|node| &*node.address == address)
+            .unwrap()
+    }
+
+    #[test]
+    fn tls_sans_and_vip_appear_once() {
+        let config = config(
+            r#"
+            [servers]
+            control = ["10.0.0.1", "10.0.0.2"]
+            worker = ["10.0.0.3"]
+            vip = "10.0.0.100"
+            tls_san = ["k8s.example.com", "10.0.0.100", "k8s.example.com"]
+            "#,
+        );
+
+        assert_eq!(
+            render_rke2_config(&config, node(&config, "10.0.0.1")),
+            "tls-san:\n  - \"10.0.0.100\"\n  - \"k8s.example.com\"\n"
+        );
+        assert_eq!(
+            render_rke2_config(&config, node(&config, "10.0.0.2")),
+            "server: \"https://10.0.0.100:6443\"\n\
+             tls-san:\n  - \"10.0.0.100\"\n  - \"k8s.example.com\"\n"
+        );
+        assert_eq!(
+            render_rke2_config(&config, node(&config, "10.0.0.3")),
+            "server: \"https://10.0.0.100:6443\"\n"
+        );
+    }
+
+    #[test]
+    fn manifests_keep_their_order() {
+        let config = config(
+            r#"
+            manifests = ["/srv/z-storage.yaml", "/srv/a-monitoring.yaml"]
+
+            [servers]
+            control = ["10.0.0.1"]
+            "#,
+        );
+
+        let targets = manifest_targets(&config)
+            .into_iter()
+            .map(|(_, target)| target)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            targets,
+            [
+                "/var/lib/rancher/rke2/server/manifests/00-z-storage.yaml",
+                "/var/lib/rancher/rke2/server/manifests/01-a-monitoring.yaml",
+            ]
+            .map(PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn data_dir_applies_to_every_node() {
+        let config = config(
+            r#"
+            data_dir = "/data/rke2"
+            manifests = ["/srv/storage.yaml"]
+
+            [servers]
+            control = ["10.0.0.1"]
+            worker = ["10.0.0.2"]
+            "#,
+        );
+
+        for address in ["10.0.0.1", "10.0.0.2"] {
+            let yaml = render_rke2_config(&config, node(&config, address));
+            assert!(yaml.contains("data-dir: \"/data/rke2\"\n"), "{yaml}");
+        }
+        assert_eq!(
+            manifest_targets(&config)[0].1,
+            Path::new("/data/rke2/server/manifests/00-storage.yaml")
+        );
+    }
+
+    #[test]
+    fn common_labels_apply_to_all_nodes_unless_overridden() {
+        let config = config(
+            r#"
+            [servers]
+            control = ["10.0.0.1"]
+            worker = [{ address = "10.0.0.2", labels = { tier = "gpu" } }]
+            common_labels = { managed-by = "ez-rke", tier = "general" }
+            "#,
+        );
+
+        let control = render_rke2_config(&config, node(&config, "10.0.0.1"));
+        assert!(control.ends_with("node-label:\n  - \"managed-by=ez-rke\"\n  - \"tier=general\"\n"));
+        let worker = render_rke2_config(&config, node(&config, "10.0.0.2"));
+        assert!(worker.ends_with("node-label:\n  - \"managed-by=ez-rke\"\n  - \"tier=gpu\"\n"));
+    }
+
+    #[test]
+    fn custom_install_script_replaces_the_stock_installer() {
+        let config = config(
+            r#"
+            [servers]
+            control = ["10.0.0.1"]
+            worker = [{ address = "10.0.0.2", install_script = "storage.sh" }, "10.0.0.3"]
+            "#,
+        );
+
+        assert_eq!(
+            install_command(&config, node(&config, "10.0.0.1")),
+            "curl -sfL 'https://get.rke2.io' | INSTALL_RKE2_CHANNEL='stable' INSTALL_RKE2_TYPE='server' sh -"
+        );
+        assert_eq!(
+            install_command(&config, node(&config, "10.0.0.2")),
+            "INSTALL_RKE2_CHANNEL='stable' INSTALL_RKE2_TYPE='agent' sh '/tmp/ez-rke-install.sh'"
+        );
+        assert_eq!(
+            install_command(&config, node(&config, "10.0.0.3")),
+            "curl -sfL 'https://get.rke2.io' | INSTALL_RKE2_CHANNEL='stable' INSTALL_RKE2_TYPE='agent' sh -"
+        );
+    }
+
+    #[test]
+    fn shell_quote_escapes_quotes() {
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn install_mirror_replaces_get_rke2_io() {
+        let config = config(
+            r#"
+            install_url_base = "https://mirror.example.com/rke2/"
+
+            [servers]
+            control = ["10.0.0.1"]
+            "#,
+        );
+
+        assert_eq!(
+            install_command(&config, node(&config, "10.0.0.1")),
+            "curl -sfL 'https://mirror.example.com/rke2/install.sh' | \
+             INSTALL_RKE2_CHANNEL='stable' INSTALL_RKE2_TYPE='server' \
+             INSTALL_RKE2_CHANNEL_URL='https://mirror.example.com/rke2/v1-release/channels' \
+             INSTALL_RKE2_GITHUB_URL='https://mirror.example.com/rke2' sh -"
+        );
+    }
+
+    #[test]
+    fn node_ips_apply_to_their_node_only() {
+        let config = config(
+            r#"
+            [servers]
+            control = [
+                { address = "node1.lan", node_ip = "10.0.0.1", node_external_ip = "203.0.113.1" },
+                "node2.lan",
+            ]
+            "#,
+        );
+
+        let yaml = render_rke2_config(&config, node(&config, "node1.lan"));
+        assert!(yaml.contains("node-ip: \"10.0.0.1\"\nnode-external-ip: \"203.0.113.1\"\n"));
+        let yaml = render_rke2_config(&config, node(&config, "node2.lan"));
+        assert!(!yaml.contains("node-ip") && !yaml.contains("node-external-ip"));
+    }
+
+    #[test]
+    fn etcd_snapshots_are_scheduled_on_servers_only() {
+        let config = config(
+            r#"
+            [servers]
+            control = ["10.0.0.1"]
+            worker = ["10.0.0.2"]
+            etcd_snapshot = { schedule_cron = "0 */6 * * *", retention = 10 }
+            "#,
+        );
+
+        let yaml = render_rke2_config(&config, node(&config, "10.0.0.1"));
+        assert!(yaml.contains(
+            "etcd-snapshot-schedule-cron: \"0 */6 * * *\"\netcd-snapshot-retention: 10\n"
+        ));
+        let yaml = render_rke2_config(&config, node(&config, "10.0.0.2"));
+        assert!(!yaml.contains("etcd-snapshot"));
+    }
+
+    #[test]
+    fn control_plane_taint_is_opt_in() {
+        let taint = "node-taint:\n  - \"node-role.kubernetes.io/control-plane:NoSchedule\"\n";
+        let toml = r#"
+            [servers]
+            control = ["10.0.0.1"]
+            worker = ["10.0.0.2"]
+        "#;
+
+        let untainted = config(toml);
+        let yaml = render_rke2_config(&untainted, node(&untainted, "10.0.0.1"));
+        assert!(!yaml.contains("node-taint"));
+
+        let config = config(&toml.replace("[servers]", "[servers]\ntaint_control_plane = true"));
+        assert!(render_rke2_config(&config, node(&config, "10.0.0.1")).contains(taint));
+        assert!(!render_rke2_config(&config, node(&config, "10.0.0.2")).contains("node-taint"));
+    }
+
+    #[test]
+    fn kubernetes_version_picks_the_install_channel() {
+        let config = config(
+            r#"
+            [servers]
+            control = ["10.0.0.1"]
+            kubernetes_version = "1.29.x"
+            "#,
+        );
+
+        assert!(install_command(&config, node(&config, "10.0.0.1"))
+            .contains("INSTALL_RKE2_CHANNEL='v1.29'"));
+    }
+
+    #[test]
+    fn ingress_vip_is_announced_but_not_a_tls_san() {
+        let config = config(
+            r#"
+            [servers]
+            control = ["10.0.0.1"]
+            vip = "10.0.0.100"
+            vips = { ingress = "10.0.0.101" }
+            "#,
+        );
+
+        let rendered = render_rke2_config(&config, node(&config, "10.0.0.1"));
+        assert!(rendered.contains("\"10.0.0.100\""));
+        assert!(!rendered.contains("10.0.0.101"));
+
+        // Each VIP gets its own kube-vip, holding its own lease
+        let manifest = kube_vip_manifest(&config).unwrap();
+        for (name, address) in [("api", "10.0.0.100"), ("ingress", "10.0.0.101")] {
+            assert!(manifest.contains(&format!("  name: kube-vip-{name}\n")));
+            assert!(manifest.contains(&format!(
+                "- name: vip_leasename\n              value: \"kube-vip-{name}\"\n"
+            )));
+            assert!(manifest.contains(&format!(
+                "- name: address\n              value: \"{address}\"\n"
+            )));
+        }
+    }
+
+    #[test]
+    fn kube_vip_manifest_announces_the_vip() {
+        let without_vip = config(
+            r#"
+            [servers]
+            control = ["10.0.0.1"]
+            "#,
+        );
+        assert_eq!(kube_vip_manifest(&without_vip), None);
+
+        let config = config(
+            r#"
+            kube_vip_version = "v0.8.0"
+
+            [servers]
+            control = ["10.0.0.1", "10.0.0.2"]
+            vip = "10.0.0.100"
+            "#,
+        );
+        let manifest = kube_vip_manifest(&config).unwrap();
+        assert!(manifest.contains("- name: address\n              value: \"10.0.0.100\"\n"));
+        assert!(manifest.contains("- name: port\n              value: \"6443\"\n"));
+        assert!(manifest.contains("image: \"ghcr.io/kube-vip/kube-vip:v0.8.0\"\n"));
+        assert_eq!(
+            kube_vip_target(&config),
+            Path::new("/var/lib/rancher/rke2/server/manifests/kube-vip.yaml")
+        );
+    }
+
+    #[test]
+    fn kube_vip_advertises_on_the_configured_interface() {
+        let bare = config(
+            r#"
+            [servers]
+            control = ["10.0.0.1"]
+            vip = "10.0.0.100"
+            "#,
+        );
+        let manifest = kube_vip_manifest(&bare).unwrap();
+        assert!(!manifest.contains("vip_interface"));
+        assert!(!manifest.contains("vip_cidr"));
+
+        let full = config(
+            r#"
+            [servers]
+            control = ["10.0.0.1"]
+            vip = { address = "10.0.0.100", interface = "eth1", cidr = 24, port = 8443 }
+            "#,
+        );
+        let manifest = kube_vip_manifest(&full).unwrap();
+        assert!(manifest.contains("- name: vip_interface\n              value: \"eth1\"\n"));
+        assert!(manifest.contains("- name: vip_cidr\n              value: \"24\"\n"));
+        assert!(manifest.contains("- name: port\n              value: \"8443\"\n"));
+    }
+}